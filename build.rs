@@ -0,0 +1,37 @@
+#[cfg(feature = "capi")]
+extern crate cbindgen;
+
+// generates the C header from src/capi.rs into OUT_DIR when the C API is
+// enabled, and warns when the checked in include/qr_encode.h no longer
+// matches it. The checked in header is what C users get, so the build never
+// writes to the source tree; regenerate it with
+//
+//   cbindgen --config cbindgen.toml --output include/qr_encode.h
+#[cfg(feature = "capi")]
+fn main() {
+    use std::fs;
+    use std::path::Path;
+
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=include/qr_encode.h");
+
+    let generated = Path::new(&out_dir).join("qr_encode.h");
+    cbindgen::Builder::new()
+        .with_src(format!("{}/src/capi.rs", crate_dir))
+        .with_config(cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).unwrap())
+        .generate()
+        .expect("unable to generate the C header")
+        .write_to_file(&generated);
+
+    let checked_in = fs::read_to_string(format!("{}/include/qr_encode.h", crate_dir)).unwrap_or_default();
+    if fs::read_to_string(&generated).unwrap_or_default() != checked_in {
+        println!("cargo:warning=include/qr_encode.h is out of date with src/capi.rs; regenerate it with \
+cbindgen --config cbindgen.toml --output include/qr_encode.h");
+    }
+}
+
+#[cfg(not(feature = "capi"))]
+fn main() {}
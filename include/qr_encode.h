@@ -0,0 +1,80 @@
+#ifndef QR_ENCODE_H
+#define QR_ENCODE_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// An encoded symbol.
+typedef struct QRCode QRCode;
+
+// Encoder settings shared by every call to `qr_encode_text`, along with the
+// message of the last error it reported.
+typedef struct QREncoder QREncoder;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates an encoder. A `version` of 0 picks the smallest version that fits
+// the text, and `ec_level` is one of 'L', 'M', 'Q' or 'H'. Invalid settings
+// make every call to `qr_encode_text` fail with a description of the problem.
+// Free it with `qr_encode_free`.
+struct QREncoder *qr_encode_new(int version, char ec_level);
+
+// Frees an encoder created by `qr_encode_new`.
+//
+// # Safety
+//
+// `encoder` must be NULL or a pointer from `qr_encode_new` that has not been freed.
+void qr_encode_free(struct QREncoder *encoder);
+
+// Encodes a NUL terminated string. Returns NULL on failure, in which case
+// `qr_encode_last_error` describes what went wrong.
+// Free the result with `qr_code_free`.
+//
+// # Safety
+//
+// `encoder` must be NULL or a live pointer from `qr_encode_new`, and `text`
+// NULL or a NUL terminated string.
+struct QRCode *qr_encode_text(struct QREncoder *encoder, const char *text);
+
+// The message of the last error reported by the encoder, or NULL if the
+// last call succeeded. The string is owned by the encoder and stays valid
+// until the next call to `qr_encode_text` or `qr_encode_free`.
+//
+// # Safety
+//
+// `encoder` must be NULL or a live pointer from `qr_encode_new`.
+const char *qr_encode_last_error(const struct QREncoder *encoder);
+
+// Number of modules along each side of the symbol, not counting the quiet zone.
+//
+// # Safety
+//
+// `code` must be NULL or a live pointer from `qr_encode_text`.
+int qr_code_width(const struct QRCode *code);
+
+// 1 if the module at column `x` and row `y` is dark, 0 if it is light and
+// -1 if the coordinates are outside the symbol.
+//
+// # Safety
+//
+// `code` must be NULL or a live pointer from `qr_encode_text`.
+int qr_code_get_module(const struct QRCode *code, int x, int y);
+
+// Frees a symbol returned by `qr_encode_text`.
+//
+// # Safety
+//
+// `code` must be NULL or a pointer from `qr_encode_text` that has not been freed.
+void qr_code_free(struct QRCode *code);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* QR_ENCODE_H */
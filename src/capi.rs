@@ -0,0 +1,161 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use qr_encoder::config::{QRConfig, ECLevel};
+use qr_encoder::error::QRError;
+use qr_encoder::qr::QR;
+use qr_encoder::version::Version;
+
+/// Encoder settings shared by every call to `qr_encode_text`, along with the
+/// message of the last error it reported.
+pub struct QREncoder {
+    // invalid settings are reported by the first call to `qr_encode_text`
+    settings: Result<(Option<Version>, ECLevel), QRError>,
+    last_error: Option<CString>
+}
+
+/// An encoded symbol.
+pub struct QRCode {
+    qr: QR,
+    width: usize
+}
+
+fn settings(version: c_int, ec_level: c_char) -> Result<(Option<Version>, ECLevel), QRError> {
+    let version = match version {
+        0 => None,
+        v if v < 0 => return Err(QRError::InvalidVersion(v as i64)),
+        v => Some(Version::new(v as usize)?)
+    };
+    let level = (ec_level as u8 as char).to_string().parse::<ECLevel>()?;
+
+    Ok((version, level))
+}
+
+/// Creates an encoder. A `version` of 0 picks the smallest version that fits
+/// the text, and `ec_level` is one of 'L', 'M', 'Q' or 'H'. Invalid settings
+/// make every call to `qr_encode_text` fail with a description of the problem.
+/// Free it with `qr_encode_free`.
+#[no_mangle]
+pub extern "C" fn qr_encode_new(version: c_int, ec_level: c_char) -> *mut QREncoder {
+    let encoder = QREncoder {
+        settings: settings(version, ec_level),
+        last_error: None
+    };
+
+    Box::into_raw(Box::new(encoder))
+}
+
+/// Frees an encoder created by `qr_encode_new`.
+///
+/// # Safety
+///
+/// `encoder` must be NULL or a pointer from `qr_encode_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn qr_encode_free(encoder: *mut QREncoder) {
+    if !encoder.is_null() {
+        drop(Box::from_raw(encoder));
+    }
+}
+
+/// Encodes a NUL terminated string. Returns NULL on failure, in which case
+/// `qr_encode_last_error` describes what went wrong.
+/// Free the result with `qr_code_free`.
+///
+/// # Safety
+///
+/// `encoder` must be NULL or a live pointer from `qr_encode_new`, and `text`
+/// NULL or a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn qr_encode_text(encoder: *mut QREncoder, text: *const c_char) -> *mut QRCode {
+    if encoder.is_null() {
+        return ptr::null_mut();
+    }
+
+    let encoder = &mut *encoder;
+    if text.is_null() {
+        encoder.last_error = CString::new("text is NULL").ok();
+        return ptr::null_mut();
+    }
+
+    let data = CStr::from_ptr(text).to_bytes().to_vec();
+    let result = encoder.settings.clone()
+        .and_then(|(version, level)| QRConfig::from_data(data, version, level));
+    match result {
+        Ok(mut config) => {
+            encoder.last_error = None;
+            let (qr, _) = config.gen_qr_code();
+            let width = qr.width();
+            Box::into_raw(Box::new(QRCode { qr, width }))
+        },
+        Err(err) => {
+            encoder.last_error = CString::new(err.to_string()).ok();
+            ptr::null_mut()
+        }
+    }
+}
+
+/// The message of the last error reported by the encoder, or NULL if the
+/// last call succeeded. The string is owned by the encoder and stays valid
+/// until the next call to `qr_encode_text` or `qr_encode_free`.
+///
+/// # Safety
+///
+/// `encoder` must be NULL or a live pointer from `qr_encode_new`.
+#[no_mangle]
+pub unsafe extern "C" fn qr_encode_last_error(encoder: *const QREncoder) -> *const c_char {
+    if encoder.is_null() {
+        return ptr::null();
+    }
+
+    match (*encoder).last_error {
+        Some(ref message) => message.as_ptr(),
+        None => ptr::null()
+    }
+}
+
+/// Number of modules along each side of the symbol, not counting the quiet zone.
+///
+/// # Safety
+///
+/// `code` must be NULL or a live pointer from `qr_encode_text`.
+#[no_mangle]
+pub unsafe extern "C" fn qr_code_width(code: *const QRCode) -> c_int {
+    if code.is_null() {
+        return 0;
+    }
+
+    (*code).width as c_int
+}
+
+/// 1 if the module at column `x` and row `y` is dark, 0 if it is light and
+/// -1 if the coordinates are outside the symbol.
+///
+/// # Safety
+///
+/// `code` must be NULL or a live pointer from `qr_encode_text`.
+#[no_mangle]
+pub unsafe extern "C" fn qr_code_get_module(code: *const QRCode, x: c_int, y: c_int) -> c_int {
+    if code.is_null() {
+        return -1;
+    }
+
+    let code = &*code;
+    if x < 0 || y < 0 || x as usize >= code.width || y as usize >= code.width {
+        return -1;
+    }
+
+    if code.qr.is_dark(y as usize, x as usize) { 1 } else { 0 }
+}
+
+/// Frees a symbol returned by `qr_encode_text`.
+///
+/// # Safety
+///
+/// `code` must be NULL or a pointer from `qr_encode_text` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn qr_code_free(code: *mut QRCode) {
+    if !code.is_null() {
+        drop(Box::from_raw(code));
+    }
+}
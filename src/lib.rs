@@ -1,5 +1,25 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[macro_use]
+extern crate alloc;
+// no_std builds get core already
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "image")]
+extern crate image as image_lib;
+#[cfg(feature = "compression")]
+extern crate miniz_oxide;
+#[cfg(feature = "signing")]
+extern crate ed25519_dalek;
+
 pub mod qr_encoder;
 
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "capi")]
+pub mod capi;
+
 // #![allow(dead_code)]
 
 // pub mod qr_encoder;
@@ -115,3 +135,808 @@ pub mod qr_encoder;
 //         assert_eq!(expected_format_bytestring, actual_format_bytestring);
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use qr_encoder::capacity::{data_bit_capacity, segment_bit_length, max_characters, fitting_versions, smallest_version};
+    use qr_encoder::cell::CellType;
+    use qr_encoder::compact::{base45_encode, base45_decode};
+    #[cfg(feature = "compression")]
+    use qr_encoder::compact::{compact, encode_compact, expand, MAX_EXPANDED_BYTES};
+    use qr_encoder::config::{ECLevel, EncodingMode, QRConfig};
+    use qr_encoder::bits::BitBuffer;
+    use qr_encoder::debug::{annotate, ModuleRole};
+    use qr_encoder::decode::decode;
+    use qr_encoder::payment::{Epc, Remittance, SwissBill, SwissAddress, SwissCurrency, SwissReference, swiss_bill_config, valid_iban, valid_bic, valid_qr_iban};
+    use qr_encoder::printer::{to_zpl, to_zpl_native, to_escpos, MAX_DOT_SIZE, DEFAULT_HEAD_DOTS};
+    use qr_encoder::qr::QR;
+    use qr_encoder::sheet::{Label, SheetLayout, A4, to_sheet_pdf, to_sheet_svgs};
+    use qr_encoder::emv::{Emv, Tlv, crc16, find, parse_emv, parse_tlv};
+    use qr_encoder::logo::{logo_area, ec_budget};
+    use qr_encoder::multiplex::{encode_multiplexed, split};
+    use qr_encoder::otp::{OtpAuth, OtpKind, base32_encode, base32_decode, clear_string};
+    use qr_encoder::payload::{Payload, Wifi, WifiSecurity, Contact, VCardVersion, Email, Sms, Event, DateTime};
+    use qr_encoder::segment::Segment;
+    #[cfg(feature = "cli")]
+    use qr_encoder::serve::serve_listener;
+    use qr_encoder::error::QRError;
+    #[cfg(feature = "signing")]
+    use qr_encoder::signed::{SigningKey, Transport, encode_signed, verify, signed_capacity, base64_encode, base64_decode, SIGNATURE_OVERHEAD};
+    use qr_encoder::stress::{stress, apply_damage, Rng, Damage, DamageKind, DamageRegion};
+    use qr_encoder::style::{Style, EyeShape, Fill, parse_color};
+    use qr_encoder::trace::json_string;
+    use qr_encoder::util::codeword_info;
+    use qr_encoder::util::tests::reference_mask;
+    use qr_encoder::version::Version;
+
+    #[test]
+    fn test_settings_errors() {
+        assert_eq!(Version::new(0), Err(QRError::InvalidVersion(0)));
+        assert_eq!(Version::new(41), Err(QRError::InvalidVersion(41)));
+        assert_eq!("-3".parse::<Version>(), Err(QRError::InvalidVersion(-3)));
+        assert_eq!("2.5".parse::<Version>(), Err(QRError::UnparsableVersion(String::from("2.5"))));
+        assert_eq!(" 40 ".parse::<Version>().map(|version| version.number()), Ok(40));
+        assert_eq!("q".parse::<ECLevel>(), Ok(ECLevel::Q));
+        assert_eq!("X".parse::<ECLevel>(), Err(QRError::InvalidECLevel(String::from("X"))));
+        assert_eq!("".parse::<ECLevel>(), Err(QRError::InvalidECLevel(String::new())));
+    }
+
+    #[test]
+    fn test_symbol_width() {
+        let mut config = QRConfig::from_data(b"hello".to_vec(), Version::new(3).ok(), ECLevel::Low).unwrap();
+        config.mirror = true;
+        let (qr, _) = config.gen_qr_code();
+        assert_eq!(qr.width(), 29);
+        assert_eq!(qr.restore().width(), 29);
+        assert_eq!(split(&qr)[0].width(), 29);
+
+        let modules = qr.modules();
+        assert!((0..29 * 29).all(|idx| qr.is_dark(idx / 29, idx % 29) == (modules[idx] == 1)));
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_wasm_options() {
+        use wasm::{parse_version, parse_size, MAX_MODULE_SIZE};
+
+        assert_eq!(parse_version(7.0).map(|version| version.number()), Ok(7));
+        assert_eq!(parse_version(2.5), Err(QRError::UnparsableVersion(String::from("2.5"))));
+        assert_eq!(parse_version(-1.0), Err(QRError::InvalidVersion(-1)));
+        assert_eq!(parse_size("moduleSize", 8.0, 1, MAX_MODULE_SIZE), Ok(8));
+        for bad in [0.0, 1.5, 33.0, 1e12, f64::NAN].iter() {
+            assert!(parse_size("moduleSize", *bad, 1, MAX_MODULE_SIZE).is_err(), "{}", bad);
+        }
+    }
+
+    #[cfg(feature = "capi")]
+    #[test]
+    fn test_capi_settings() {
+        use std::ffi::CStr;
+        use capi::{qr_encode_new, qr_encode_text, qr_encode_last_error, qr_encode_free, qr_code_width, qr_code_free};
+
+        unsafe {
+            let encoder = qr_encode_new(-1, b'M' as _);
+            assert!(qr_encode_text(encoder, b"hello\0".as_ptr() as _).is_null());
+            let message = CStr::from_ptr(qr_encode_last_error(encoder)).to_str().unwrap();
+            assert_eq!(message, QRError::InvalidVersion(-1).to_string());
+            qr_encode_free(encoder);
+
+            let encoder = qr_encode_new(0, b'M' as _);
+            let code = qr_encode_text(encoder, b"hello\0".as_ptr() as _);
+            assert_eq!(qr_code_width(code), 21);
+            qr_code_free(code);
+            qr_encode_free(encoder);
+        }
+    }
+
+    fn segment_bits(segment: Segment, version: usize) -> String {
+        let mut buffer = BitBuffer::new();
+        segment.write(&mut buffer, version);
+        buffer.to_bit_string()
+    }
+
+    #[test]
+    fn test_annotate() {
+        // version 2-L has 34 data and 10 error correction codewords, then 7 remainder bits
+        let mut config = QRConfig::from_data(b"annotate me".to_vec(), Some(Version::new(2).unwrap()), ECLevel::Low).unwrap();
+        config.mirror = true;
+        let (qr, tracker) = config.gen_qr_code();
+        let width = qr.width();
+        let annotations = annotate(&qr, &tracker, 34);
+
+        let expected = reference_mask(2);
+        let mut bits = vec![vec![]; 44];
+        let mut remainder = 0;
+        for (idx, annotation) in annotations.iter().enumerate() {
+            match annotation.role {
+                ModuleRole::Function(_) => assert!(expected[idx], "module {} isn't a function module", idx),
+                _ if expected[idx] => panic!("function module {} was annotated as {:?}", idx, annotation.role),
+                ModuleRole::Data { codeword, bit } => {
+                    assert!(codeword < 34);
+                    bits[codeword].push(bit);
+                },
+                ModuleRole::Ecc { codeword, bit } => {
+                    assert!(codeword >= 34);
+                    bits[codeword].push(bit);
+                },
+                ModuleRole::Remainder => remainder += 1
+            }
+        }
+        assert_eq!(remainder, 7);
+        // every bit of every codeword lands on exactly one module
+        for placed in bits.iter_mut() {
+            placed.sort();
+            assert_eq!(*placed, vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        }
+
+        // placement starts in the bottom right corner of the unmirrored symbol
+        let corner = &annotations[width * width - 1].role;
+        assert!(matches!(*corner, ModuleRole::Data { codeword: 0, bit: 7 }) && corner.is_first_bit());
+        assert!(matches!(annotations[width * width - 2].role, ModuleRole::Data { codeword: 0, bit: 6 }));
+        assert!(matches!(annotations[0].role, ModuleRole::Function(CellType::Finder)));
+        assert!(matches!(annotations[6 * width + 8].role, ModuleRole::Function(CellType::Timing)));
+        assert!(matches!(annotations[(width - 8) * width + 8].role, ModuleRole::Function(CellType::DarkModule)));
+        assert_eq!(annotations[0].role.codeword(), None);
+    }
+
+    #[test]
+    fn test_segment_bits() {
+        // the worked examples from the spec
+        assert_eq!(
+            segment_bits(Segment::numeric(b"01234567").unwrap(), 1),
+            "00010000001000000000110001010110011000011"
+        );
+        assert_eq!(
+            segment_bits(Segment::alphanumeric(b"AC-42").unwrap(), 1),
+            "00100000001010011100111011100111001000010"
+        );
+        assert_eq!(segment_bits(Segment::kanji(&[0x93, 0x5F, 0xE4, 0xAA]).unwrap(), 1), "10000000001001101100111111101010101010");
+        assert_eq!(segment_bits(Segment::eci(9).unwrap(), 1), "011100001001");
+        assert!(Segment::alphanumeric(b"ac").is_err());
+    }
+
+    #[test]
+    fn test_capacity_limits() {
+        // the corners of the capacity table in the spec
+        let v = |number: usize| Version::new(number).unwrap();
+        assert_eq!(max_characters(v(1), &ECLevel::Low, &EncodingMode::Numeric), 41);
+        assert_eq!(max_characters(v(1), &ECLevel::Low, &EncodingMode::AlphaNumeric), 25);
+        assert_eq!(max_characters(v(1), &ECLevel::Low, &EncodingMode::Byte), 17);
+        assert_eq!(max_characters(v(1), &ECLevel::Low, &EncodingMode::Japanese), 10);
+        assert_eq!(max_characters(v(1), &ECLevel::High, &EncodingMode::Numeric), 17);
+        assert_eq!(max_characters(v(40), &ECLevel::Low, &EncodingMode::Numeric), 7089);
+        assert_eq!(max_characters(v(40), &ECLevel::Low, &EncodingMode::AlphaNumeric), 4296);
+        assert_eq!(max_characters(v(40), &ECLevel::Low, &EncodingMode::Byte), 2953);
+        assert_eq!(max_characters(v(40), &ECLevel::Low, &EncodingMode::Japanese), 1817);
+        assert_eq!(max_characters(v(40), &ECLevel::High, &EncodingMode::Byte), 1273);
+        // capacities take a checked version, so none either side of the table
+        // can be asked for, even through a config's version field
+        assert_eq!(Version::largest(), v(40));
+        for &number in [0, 41].iter() {
+            let mut config = QRConfig::new(v(1), b"1".to_vec(), ECLevel::Low);
+            config.version = number;
+            assert_eq!(config.max_ec_level(), ECLevel::Low);
+        }
+        assert_eq!(data_bit_capacity(Version::largest(), &ECLevel::Low), 2956 * 8);
+        assert_eq!(segment_bit_length(3, &EncodingMode::Numeric, v(10)), 4 + 12 + 10);
+
+        // 17 bytes only fit version 1 at L, with the 4 bit terminator to spare;
+        // one more moves up a version
+        let fits = fitting_versions(&[b'a'; 17], &EncodingMode::Byte);
+        assert_eq!((fits[0].version, fits[0].err_correction_level, fits[0].remaining_bits), (1, ECLevel::Low, 4));
+        assert_eq!(fits[1].version, 2);
+        let fits = fitting_versions(&[b'a'; 18], &EncodingMode::Byte);
+        let levels: Vec<_> = fits.iter().filter(|fit| fit.version == 2).map(|fit| fit.err_correction_level).collect();
+        assert_eq!(levels, vec![ECLevel::Low, ECLevel::Medium, ECLevel::Q]);
+        assert_eq!(fits.first().map(|fit| fit.version), Some(2));
+
+        // past the largest symbol nothing fits
+        assert_eq!(fitting_versions(&[b'a'; 2953], &EncodingMode::Byte).len(), 1);
+        assert!(fitting_versions(&[b'a'; 2954], &EncodingMode::Byte).is_empty());
+        assert_eq!(smallest_version(&[b'a'; 2953], &EncodingMode::Byte, &ECLevel::Low).unwrap().number(), 40);
+        assert!(matches!(
+            smallest_version(&[b'a'; 2954], &EncodingMode::Byte, &ECLevel::Low),
+            Err(QRError::DataTooLong { length: 2954, capacity: 2953 })
+        ));
+    }
+
+    #[test]
+    fn test_max_ec_level() {
+        // version 2 holds 32, 26, 20 and 14 bytes at L, M, Q and H
+        let version = Version::new(2).unwrap();
+        let level_for = |length: usize, level: ECLevel| QRConfig::new(version, vec![b'a'; length], level).max_ec_level();
+        assert_eq!(level_for(14, ECLevel::Low), ECLevel::High);
+        assert_eq!(level_for(15, ECLevel::Low), ECLevel::Q);
+        assert_eq!(level_for(20, ECLevel::Medium), ECLevel::Q);
+        assert_eq!(level_for(26, ECLevel::Low), ECLevel::Medium);
+        assert_eq!(level_for(27, ECLevel::Low), ECLevel::Low);
+        // never lower than the level asked for, even when the data doesn't fit it
+        assert_eq!(level_for(20, ECLevel::High), ECLevel::High);
+
+        // the raised level is the one the symbol is built and decoded with
+        let mut config = QRConfig::from_data(vec![b'a'; 15], Some(version), ECLevel::Low).unwrap();
+        config.maximise_ec_level = true;
+        let (qr, _) = config.gen_qr_code();
+        assert_eq!(config.err_correction_level, ECLevel::Q);
+        assert_eq!(qr.err_correction_level, ECLevel::Q);
+        assert_eq!(decode(&qr).unwrap(), vec![b'a'; 15]);
+    }
+
+    #[test]
+    fn test_trace_json() {
+        let mut config = QRConfig::from_data(b"HELLO\"1".to_vec(), Version::new(1).ok(), ECLevel::Low).unwrap();
+        let (_, _, trace) = config.gen_qr_code_with_trace();
+        assert_eq!(trace.blocks.len(), 1);
+        assert_eq!(trace.blocks[0].ecc.len(), 7);
+        assert_eq!(trace.data_codewords.len(), 19);
+
+        let json = trace.to_json();
+        assert!(json.starts_with("{\"version\":1,\"err_correction_level\":\"Low\",\"segments\":[{\"mode\":\"Byte\",\"char_count\":7,"));
+        assert!(json.ends_with(&format!("\"mask\":{},\"format_bits\":\"{:015b}\",\"version_bits\":null}}", trace.mask, trace.format_bits)));
+        assert_eq!(json.matches("\"pattern\":").count(), trace.mask_penalties.len());
+        assert_eq!(json_string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+
+        // every pattern the search tries is scored, and the lowest total is the one applied
+        assert_eq!(trace.mask_penalties.iter().map(|penalty| penalty.pattern).collect::<Vec<_>>(), (0..8).collect::<Vec<_>>());
+        let lowest = trace.mask_penalties.iter().map(|penalty| penalty.total).min();
+        assert_eq!(Some(trace.mask_penalties[trace.mask].total), lowest);
+        assert!(trace.mask_penalties.iter().all(|penalty| penalty.scores.iter().sum::<usize>() == penalty.total));
+    }
+
+    // the four penalties of ISO 18004 7.8.3 for a printed symbol, worked out
+    // from its modules alone. Past the edge is the light quiet zone.
+    fn reference_penalties(qr: &QR) -> [usize; 4] {
+        let width = qr.width();
+        let dark = |row: usize, col: usize| qr.body[row * width + col].is_black();
+        let lines: Vec<Vec<bool>> = (0..width).map(|row| (0..width).map(|col| dark(row, col)).collect())
+            .chain((0..width).map(|col| (0..width).map(|row| dark(row, col)).collect()))
+            .collect();
+
+        let mut scores = [0; 4];
+        for line in lines.iter() {
+            let mut start = 0;
+            while start < width {
+                let length = line[start..].iter().take_while(|&&module| module == line[start]).count();
+                if length >= 5 {
+                    scores[0] += 3 + length - 5;
+                }
+                start += length;
+            }
+
+            let at = |i: isize| i >= 0 && (i as usize) < width && line[i as usize];
+            for i in 0..(width as isize - 6) {
+                let finder = [true, false, true, true, true, false, true].iter().enumerate().all(|(j, &module)| at(i + j as isize) == module);
+                let before = (1..5).all(|j| !at(i - j));
+                let after = (7..11).all(|j| !at(i + j));
+                if finder && (before || after) {
+                    scores[2] += 40;
+                }
+            }
+        }
+
+        for row in 0..(width - 1) {
+            for col in 0..(width - 1) {
+                let colour = dark(row, col);
+                if dark(row + 1, col) == colour && dark(row, col + 1) == colour && dark(row + 1, col + 1) == colour {
+                    scores[1] += 3;
+                }
+            }
+        }
+
+        let total = (width * width) as f64;
+        let share = qr.body.iter().filter(|cell| cell.is_black()).count() as f64 / total * 100.0;
+        scores[3] = ((share - 50.0).abs() / 5.0).floor() as usize * 10;
+        scores
+    }
+
+    #[test]
+    fn test_mask_selection() {
+        let cases = [(&b"mask search"[..], 1, ECLevel::Low), (&b"HELLO WORLD"[..], 2, ECLevel::Medium), (&b"version information too"[..], 7, ECLevel::Q)];
+        for &(data, version, level) in cases.iter() {
+            let mut config = QRConfig::from_data(data.to_vec(), Version::new(version).ok(), level).unwrap();
+            let (_, _, trace) = config.gen_qr_code_with_trace();
+
+            // each pattern's symbol, as printed, scores what the search recorded for it
+            let mut totals = vec![];
+            for pattern in 0..8 {
+                let mut fixed = QRConfig::from_data(data.to_vec(), Version::new(version).ok(), level).unwrap();
+                fixed.fixed_mask = Some(pattern);
+                let (qr, _) = fixed.gen_qr_code();
+                let scores = reference_penalties(&qr);
+                assert_eq!(trace.mask_penalties[pattern].scores, scores, "version {} pattern {}", version, pattern);
+                totals.push(scores.iter().sum::<usize>());
+            }
+
+            // all eight are searched, and the first with the lowest penalty is picked
+            let lowest = totals.iter().min().unwrap();
+            assert_eq!(Some(trace.mask), totals.iter().position(|total| total == lowest), "version {}", version);
+        }
+    }
+
+    #[test]
+    fn test_terminator_and_padding() {
+        let mut buffer = BitBuffer::new();
+        Segment::numeric(b"01234567").unwrap().write(&mut buffer, 1);
+        let padding = buffer.terminate(16 * 8);
+        assert_eq!(padding.terminator_bits, 4);
+        assert_eq!(padding.bit_padding, 3);
+        assert_eq!(
+            buffer.bytes(),
+            &[0x10, 0x20, 0x0C, 0x56, 0x61, 0x80, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11][..]
+        );
+
+        // a terminator is cut short when the capacity runs out
+        let mut buffer = BitBuffer::new();
+        buffer.append(0, 14);
+        let padding = buffer.terminate(16);
+        assert_eq!(padding.terminator_bits, 2);
+        assert_eq!(padding.bit_padding, 0);
+        assert!(padding.pad_codewords.is_empty());
+    }
+
+    #[test]
+    fn test_style_check() {
+        let mut style = Style::default();
+        for shape in [EyeShape::Square, EyeShape::Rounded, EyeShape::Circle].iter() {
+            style.eyes.outer = *shape;
+            style.eyes.inner = *shape;
+            assert!(style.check().is_ok(), "{:?} eyes", shape);
+        }
+
+        style.fill = Fill::Solid(parse_color("#aaaaaa").unwrap());
+        assert!(style.check().is_err());
+    }
+
+    #[test]
+    fn test_logo_area() {
+        let mut config = QRConfig::from_data(b"hello".to_vec(), Version::new(5).ok(), ECLevel::High).unwrap();
+        let (qr, tracker) = config.gen_qr_code();
+
+        // the automatic size stays in budget, an oversized one is cut down to the symbol's edges
+        let auto = logo_area(&qr, &tracker, 5, None, 1).unwrap();
+        assert!(auto.logo_size() > 0 && auto.is_safe());
+        let large = logo_area(&qr, &tracker, 5, Some(30), 1).unwrap();
+        assert!(large.row >= 9 && large.row + large.size <= qr.width() - 9 && !large.is_safe());
+
+        // a quiet box wider than the symbol is refused rather than underflowing
+        assert!(logo_area(&qr, &tracker, 5, Some(18), 40).is_err());
+
+        // version 5-H has two blocks of 11 data codewords and two of 12, each
+        // with 22 error correction codewords, so each block can safely lose 6
+        let blocks = codeword_info(5, &ECLevel::High).interleaved_blocks();
+        assert_eq!(blocks.len(), 134);
+        assert_eq!(&blocks[40..48], &[0, 1, 2, 3, 2, 3, 0, 1]);
+        assert_eq!((0..4).map(|block| blocks.iter().filter(|&&b| b == block).count()).collect::<Vec<_>>(), vec![33, 33, 34, 34]);
+        assert_eq!(ec_budget(5, &ECLevel::High), 6);
+        // the automatic size keeps every block in budget, even when the whole
+        // logo covers more than one block could lose
+        assert!(auto.worst_block <= auto.budget && auto.damaged_codewords > auto.budget);
+        assert!(large.worst_block > large.budget);
+    }
+
+    #[test]
+    fn test_multiplex_round_trip() {
+        let payloads: [&[u8]; 3] = [b"red", b"a longer green message", b"blue"];
+        let qr = encode_multiplexed(payloads, None, ECLevel::Medium).unwrap();
+        let channels = split(&qr);
+
+        // every channel is the message on its own, all with the same mask
+        let matches = (0..8).filter(|mask| {
+            payloads.iter().zip(channels.iter()).all(|(payload, channel)| {
+                let mut config = QRConfig::from_data(payload.to_vec(), Version::new(2).ok(), ECLevel::Medium).unwrap();
+                config.fixed_mask = Some(*mask);
+                let (expected, _) = config.gen_qr_code();
+                expected.modules() == channel.modules()
+            })
+        }).count();
+        assert_eq!(matches, 1);
+    }
+
+    #[test]
+    fn test_invert_and_mirror() {
+        let mut config = QRConfig::from_data(b"through glass".to_vec(), None, ECLevel::Medium).unwrap();
+        let (plain, _) = config.gen_qr_code();
+        config.invert = true;
+        config.mirror = true;
+        let (flipped, _) = config.gen_qr_code();
+
+        assert!(flipped.inverted && flipped.mirrored);
+        let width = plain.width();
+        for row in 0..width {
+            for col in 0..width {
+                assert_eq!(plain.is_dark(row, col), !flipped.is_dark(row, width - 1 - col));
+            }
+        }
+        assert_eq!(flipped.restore().modules(), plain.modules());
+    }
+
+    #[test]
+    fn test_decode_round_trip() {
+        for (data, level) in [(&b"HELLO WORLD 123"[..], ECLevel::Q), (&b"lower case bytes, in a bigger symbol"[..], ECLevel::High)].iter() {
+            let mut config = QRConfig::from_data(data.to_vec(), Some(Version::new(7).unwrap()), *level).unwrap();
+            config.mirror = true;
+            let (qr, _) = config.gen_qr_code();
+            assert_eq!(decode(&qr).unwrap(), data.to_vec());
+        }
+
+        // H recovers a third of its codewords when they're known to be erased, L doesn't
+        let damage = [Damage { kind: DamageKind::Erase, region: DamageRegion::Burst(200) }];
+        let results = stress(b"stress test", Some(Version::new(3).unwrap()), &damage, 10, 1).unwrap();
+        assert_eq!(results.len(), 4);
+        assert!(results[0].successes < results[3].successes);
+        assert_eq!(results[3].successes, 10);
+
+        // flips and erasures together, corrected while they fit and failed trials once they don't
+        let light = [Damage { kind: DamageKind::Erase, region: DamageRegion::Burst(40) }, Damage { kind: DamageKind::Flip, region: DamageRegion::Random(4) }];
+        let results = stress(b"stress test", Some(Version::new(3).unwrap()), &light, 10, 1).unwrap();
+        assert_eq!(results[3].successes, 10);
+        let heavy = [Damage { kind: DamageKind::Erase, region: DamageRegion::Burst(150) }, Damage { kind: DamageKind::Flip, region: DamageRegion::Random(20) }];
+        let results = stress(b"hello world", None, &heavy, 50, 1).unwrap();
+        assert!(results.iter().all(|result| result.trials == 50 && result.successes < 50));
+        // without a version every level shares the one the data needs at H
+        assert!(results.len() == 4 && results.iter().all(|result| result.version == 2));
+        assert_eq!("foo:1".parse::<DamageRegion>(), Err(QRError::InvalidDamage(String::from("foo:1"))));
+
+        // random damage picks distinct modules, up to every module of the symbol
+        let mut rng = Rng::new(1);
+        for &count in [100, 441, 1000].iter() {
+            let (mut modules, mut erased) = (vec![0u8; 441], vec![false; 441]);
+            apply_damage(&mut modules, &mut erased, 21, &Damage { kind: DamageKind::Flip, region: DamageRegion::Random(count) }, &mut rng);
+            assert_eq!(modules.iter().filter(|&&module| module == 1).count(), count.min(441));
+        }
+
+        // data too long for the version asked for fails as too long, at what the version holds at L
+        let results = stress(&[b'a'; 20], Version::new(1).ok(), &damage, 1, 1);
+        assert_eq!(results.unwrap_err(), QRError::DataTooLong { length: 20, capacity: 17 });
+    }
+
+    #[test]
+    fn test_payloads() {
+        let wifi = Wifi { ssid: String::from("cafe;guest:5G"), password: String::from("p\\ss,word"), security: WifiSecurity::Wpa, hidden: true };
+        assert_eq!(wifi.payload().unwrap(), "WIFI:T:WPA;S:cafe\\;guest\\:5G;P:p\\\\ss\\,word;H:true;;");
+
+        // long lines fold at 75 bytes without splitting characters
+        let contact = Contact {
+            family_name: String::from("Doe"),
+            given_name: String::from("Jane"),
+            note: "é".repeat(40),
+            ..Contact::default()
+        };
+        let vcard = contact.vcard(VCardVersion::V3).payload().unwrap();
+        assert!(vcard.starts_with("BEGIN:VCARD\r\nVERSION:3.0\r\nN:Doe;Jane;;;\r\nFN:Jane Doe\r\n"));
+        assert!(vcard.contains(&format!("NOTE:{}\r\n {}\r\n", "é".repeat(35), "é".repeat(5))));
+        assert_eq!(contact.mecard().payload().unwrap(), "MECARD:N:Doe,Jane;NOTE:".to_string() + &"é".repeat(40) + ";;");
+
+        // days are checked against the month, leap years included
+        let event = |year: u16, month: u8, day: u8| Event {
+            summary: String::from("Launch"),
+            start: DateTime { year, month, day, hour: 9, minute: 30, second: 0, utc: true },
+            end: None,
+            location: String::new(),
+            description: String::new()
+        }.payload();
+        assert!(event(2028, 2, 29).unwrap().contains("DTSTART:20280229T093000Z\r\n"));
+        assert!(event(2000, 2, 29).is_ok());
+        assert!(event(2026, 2, 29).is_err());
+        assert!(event(1900, 2, 29).is_err());
+        assert!(event(2026, 2, 31).is_err());
+        assert!(event(2026, 4, 31).is_err());
+        assert!(event(2026, 12, 31).is_ok());
+
+        let email = Email { to: String::from("jane@example.com"), subject: String::from("Hi there & welcome"), ..Email::default() };
+        assert_eq!(email.payload().unwrap(), "mailto:jane@example.com?subject=Hi%20there%20%26%20welcome");
+
+        // upper case payloads get the cheaper alphanumeric mode
+        let config = wifi.config(None, ECLevel::Medium).unwrap();
+        assert_eq!(config.message_segments()[0].mode(), Some(EncodingMode::Byte));
+        let sms = Sms { number: String::from("+441234567890"), message: String::from("CALL ME") };
+        let config = sms.config(None, ECLevel::Medium).unwrap();
+        assert_eq!(config.message_segments()[0].mode(), Some(EncodingMode::AlphaNumeric));
+    }
+
+    #[test]
+    fn test_epc_payment() {
+        assert!(valid_iban("DE89 3704 0044 0532 0130 00"));
+        assert!(!valid_iban("DE88 3704 0044 0532 0130 00"));
+        assert!(valid_bic("COBADEFFXXX") && !valid_bic("COBADE"));
+
+        let mut payment = Epc {
+            bic: String::from("BFSWDE33BER"),
+            name: String::from("Wikimedia Foerdergesellschaft"),
+            iban: String::from("DE33 1002 0500 0001 1947 00"),
+            amount: Some(String::from("123.45")),
+            purpose: String::new(),
+            remittance: Remittance::Text(String::from("Spende fuer Wikipedia")),
+            information: String::new()
+        };
+        assert_eq!(
+            payment.payload().unwrap(),
+            "BCD\n002\n1\nSCT\nBFSWDE33BER\nWikimedia Foerdergesellschaft\nDE33100205000001194700\nEUR123.45\n\n\nSpende fuer Wikipedia"
+        );
+        let config = payment.config(None, ECLevel::Medium).unwrap();
+        assert!(config.version <= 13);
+        assert!(payment.config(None, ECLevel::High).is_err());
+
+        payment.amount = Some(String::from("12.345"));
+        assert!(payment.payload().is_err());
+        payment.amount = None;
+        payment.remittance = Remittance::Reference(String::from("RF19 5390 0754 7034"));
+        assert!(payment.payload().is_err());
+        payment.remittance = Remittance::Reference(String::from("RF18 5390 0754 7034"));
+        assert!(payment.payload().unwrap().ends_with("\nRF18539007547034"));
+    }
+
+    #[test]
+    fn test_swiss_bill() {
+        assert!(valid_qr_iban("CH44 3199 9123 0008 8901 2"));
+        assert!(!valid_qr_iban("CH93 0076 2011 6238 5295 7"));
+
+        let creditor = SwissAddress {
+            name: String::from("Robert Schneider AG"),
+            street: String::from("Rue du Lac"),
+            building_number: String::from("1268"),
+            postcode: String::from("2501"),
+            town: String::from("Biel"),
+            country: String::from("CH")
+        };
+        let mut bill = SwissBill {
+            iban: String::from("CH44 3199 9123 0008 8901 2"),
+            creditor,
+            amount: Some(String::from("1949.7")),
+            currency: SwissCurrency::Chf,
+            debtor: None,
+            reference: SwissReference::Qr(String::from("21 00000 00003 13947 14300 09017")),
+            message: String::from("Order of 15 June 2020"),
+            billing: String::new()
+        };
+        let payload = bill.payload().unwrap();
+        let lines: Vec<&str> = payload.split('\n').collect();
+        assert_eq!(lines.len(), 31);
+        assert_eq!(&lines[..5], &["SPC", "0200", "1", "CH4431999123000889012", "S"]);
+        assert_eq!(&lines[18..20], &["1949.70", "CHF"]);
+        assert_eq!(&lines[27..], &["QRR", "210000000003139471430009017", "Order of 15 June 2020", "EPD"]);
+        assert_eq!(bill.config(None, ECLevel::Medium).unwrap().err_correction_level, ECLevel::Medium);
+
+        // a QR-IBAN can only be used with a QR reference, and the check digit has to match
+        bill.reference = SwissReference::None;
+        assert!(bill.payload().is_err());
+        bill.reference = SwissReference::Qr(String::from("21 00000 00003 13947 14300 09018"));
+        assert!(bill.payload().is_err());
+
+        // payloads made elsewhere are read back and checked the same way
+        assert_eq!(SwissBill::parse(&payload.replace('\n', "\r\n")).unwrap().creditor.town, "Biel");
+        assert_eq!(swiss_bill_config(payload.as_bytes(), None).unwrap().err_correction_level, ECLevel::Medium);
+        assert!(swiss_bill_config(b"hello", None).is_err());
+        assert!(swiss_bill_config(payload.replace("QRR", "NON").as_bytes(), None).is_err());
+        assert!(swiss_bill_config(payload.as_bytes(), Version::new(30).ok()).is_err());
+    }
+
+    #[test]
+    fn test_emv_payload() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+
+        let emv = Emv {
+            dynamic: false,
+            accounts: vec![Tlv::merchant_account(26, "A000000677010111", vec![Tlv::primitive(1, "0066812345678")])],
+            category: String::from("5812"),
+            currency: String::from("764"),
+            amount: Some(String::from("120.50")),
+            country: String::from("TH"),
+            merchant_name: String::from("Noodle Shop"),
+            merchant_city: String::from("Bangkok"),
+            postal_code: String::new(),
+            additional: vec![Tlv::primitive(1, "INV-0042")],
+            extra: vec![]
+        };
+        let payload = emv.payload().unwrap();
+        assert!(payload.starts_with("00020101021126370016A000000677010111011300668123456785204581253037645406120.505802TH"));
+
+        let fields = parse_emv(&payload).unwrap();
+        assert_eq!(find(&fields, 26).and_then(|account| account.field(1)).and_then(|id| id.text()), Some("0066812345678"));
+        assert_eq!(find(&fields, 62).and_then(|data| data.field(1)).and_then(|bill| bill.text()), Some("INV-0042"));
+        let mut tampered = payload.clone();
+        tampered.replace_range(60..61, "9");
+        assert!(parse_emv(&tampered).is_err());
+
+        // extra fields can't repeat the tags the payload writes itself
+        for &tag in [0, 1, 26, 52, 62, 63].iter() {
+            let extra = Emv { extra: vec![Tlv::primitive(tag, "ABCD")], ..emv.clone() };
+            assert!(extra.payload().is_err(), "tag {}", tag);
+        }
+        let unreserved = Emv { extra: vec![Tlv::template(80, vec![Tlv::primitive(0, "com.example")])], ..emv.clone() };
+        let fields = parse_emv(&unreserved.payload().unwrap()).unwrap();
+        assert_eq!(find(&fields, 80).and_then(|template| template.field(0)).and_then(|id| id.text()), Some("com.example"));
+
+        // a CRC hidden at the end of another field's value isn't tag 63
+        let hidden = String::from("0002015912ABCD6304");
+        let hidden = format!("{}{:04X}", hidden, crc16(hidden.as_bytes()));
+        assert!(parse_emv(&hidden).is_err());
+
+        // tags and lengths are two ASCII digits, without signs
+        assert!(parse_tlv("00+201").is_err());
+        assert!(parse_tlv("+10101").is_err());
+        assert_eq!(parse_tlv("000201").unwrap(), vec![Tlv::primitive(0, "01")]);
+
+        // the mixed case name and city go in bytes, the rest stays alphanumeric
+        let config = emv.config(None, ECLevel::Medium).unwrap();
+        let modes: Vec<_> = config.message_segments().iter().map(|segment| segment.mode()).collect();
+        assert!(modes.contains(&Some(EncodingMode::AlphaNumeric)) && modes.contains(&Some(EncodingMode::Byte)));
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        let secret = b"12345678901234567890";
+        assert_eq!(base32_encode(secret), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(base32_decode("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").unwrap(), secret.to_vec());
+
+        let mut otp = OtpAuth::totp("ACME Co", "jane@example.com", secret);
+        let uri = "otpauth://totp/ACME%20Co:jane@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=ACME%20Co";
+        assert_eq!(otp.payload().unwrap(), uri);
+        assert!(!format!("{:?}", otp).contains("GEZD") && !format!("{:?}", otp).contains("49, 50"));
+
+        let (qr, _) = otp.config(None, ECLevel::Medium).unwrap().gen_qr_code();
+        assert_eq!(decode(&qr).unwrap(), uri.as_bytes().to_vec());
+
+        otp.kind = OtpKind::Hotp { counter: 7 };
+        otp.digits = 8;
+        assert!(otp.payload().unwrap().ends_with("&digits=8&counter=7"));
+        otp.secret.truncate(10);
+        assert!(otp.payload().is_err());
+
+        let mut payload = String::from("otpauth://totp/a?secret=GEZDGNBV");
+        clear_string(&mut payload);
+        assert!(payload.is_empty());
+    }
+
+    #[test]
+    fn test_compact_payload() {
+        // the examples from RFC 9285
+        assert_eq!(base45_encode(b"AB"), "BB8");
+        assert_eq!(base45_encode(b"Hello!!"), "%69 VD92EX0");
+        assert_eq!(base45_encode(b"ietf!"), "QED8WEX0");
+        assert_eq!(base45_decode(b"QED8WEX0").unwrap(), b"ietf!".to_vec());
+        assert!(base45_decode(b"GGW").is_err());
+        assert!(base45_decode(b"ABCD").is_err());
+
+        #[cfg(feature = "compression")]
+        {
+            let data = "{\"name\":\"Jane\",\"vaccinations\":[".to_string() + &"{\"dose\":1,\"product\":\"EU/1/20/1528\"},".repeat(8) + "]}";
+            let mut config = encode_compact(data.as_bytes(), None, ECLevel::Medium).unwrap();
+            let compact_version = config.version;
+            let (qr, _) = config.gen_qr_code();
+            assert_eq!(expand(&decode(&qr).unwrap()).unwrap(), data.as_bytes().to_vec());
+
+            let plain = QRConfig::from_data(data.into_bytes(), None, ECLevel::Medium).unwrap();
+            assert!(compact_version < plain.version);
+
+            // a payload that inflates past the limit is refused
+            let bomb = compact(&vec![0; MAX_EXPANDED_BYTES + 1]);
+            assert!(bomb.len() < 4296 && expand(bomb.as_bytes()).is_err());
+        }
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_signed_payload() {
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg");
+        assert_eq!(base64_decode(b"Zm9vYg").unwrap(), b"foob".to_vec());
+
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let public_key = key.verifying_key();
+        let data = b"ASSET-000417 ROOM 2.14";
+
+        for transport in [Transport::Binary, Transport::Base45, Transport::Base64].iter() {
+            let mut config = encode_signed(data, &key, *transport, None, ECLevel::Medium).unwrap();
+            let version = config.version;
+            assert!(signed_capacity(Version::new(version).unwrap(), &ECLevel::Medium, *transport) >= data.len());
+            assert!(signed_capacity(Version::new(version - 1).unwrap(), &ECLevel::Medium, *transport) < data.len());
+
+            let (qr, _) = config.gen_qr_code();
+            let decoded = decode(&qr).unwrap();
+            assert_eq!(verify(&decoded, &public_key).unwrap(), data.to_vec());
+        }
+
+        let mut config = encode_signed(data, &key, Transport::Binary, None, ECLevel::Medium).unwrap();
+        let (qr, _) = config.gen_qr_code();
+        let mut tampered = decode(&qr).unwrap();
+        assert_eq!(tampered.len(), data.len() + SIGNATURE_OVERHEAD);
+        tampered[5] ^= 1;
+        assert_eq!(verify(&tampered, &public_key), Err(QRError::InvalidSignature));
+
+        let too_long = vec![0; signed_capacity(Version::new(5).unwrap(), &ECLevel::Medium, Transport::Binary) + 1];
+        assert!(encode_signed(&too_long, &key, Transport::Binary, Version::new(5).ok(), ECLevel::Medium).is_err());
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn test_serve() {
+        use std::io::{Read, Write};
+        use std::net::{TcpListener, TcpStream};
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve_listener(listener));
+
+        let request = |raw: String| -> String {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(raw.as_bytes()).unwrap();
+            let mut response = vec![];
+            stream.read_to_end(&mut response).unwrap();
+            String::from_utf8_lossy(&response).into_owned()
+        };
+        let get = |target: &str, headers: &str| request(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n{}\r\n", target, headers));
+
+        let response = get("/qr?data=hello%20world&format=svg&scale=2", "");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: image/svg+xml\r\n"));
+        let etag = response.lines().find(|line| line.starts_with("ETag: ")).unwrap()[6..].to_string();
+        assert!(get("/qr?data=hello%20world&format=svg&scale=2", &format!("If-None-Match: {}\r\n", etag)).starts_with("HTTP/1.1 304"));
+        assert!(!get("/qr?data=hello%20world&format=svg&scale=3", "").contains(&etag));
+
+        let body = "{\"data\": \"hello\", \"ec\": \"q\", \"format\": \"png\"}";
+        let response = request(format!("POST /qr HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n") && response.contains("\u{fffd}PNG"));
+
+        assert!(get("/qr?data=hello&ec=x", "").starts_with("HTTP/1.1 400"));
+        assert!(get("/qr?data=hello&scale=500", "").starts_with("HTTP/1.1 400"));
+        assert!(get(&format!("/qr?data={}&ec=h", "a".repeat(2000)), "").starts_with("HTTP/1.1 422"));
+        assert!(get(&format!("/qr?data={}", "a".repeat(3000)), "").starts_with("HTTP/1.1 413"));
+        assert!(get("/other", "").starts_with("HTTP/1.1 404"));
+        assert!(request(String::from("DELETE /qr HTTP/1.1\r\n\r\n")).starts_with("HTTP/1.1 405"));
+    }
+
+    #[test]
+    fn test_printer_commands() {
+        let (qr, _) = QRConfig::from_data(b"HELLO".to_vec(), Version::new(1).ok(), ECLevel::Low).unwrap().gen_qr_code();
+        let dark = qr.modules().iter().filter(|&&module| module == 1).count() as u32;
+
+        // 21 modules and a quiet zone of 4 at 2 dots a module is 58 dots, 8 bytes a row
+        let escpos = to_escpos(&qr, 2, 4, DEFAULT_HEAD_DOTS).unwrap();
+        assert_eq!(&escpos[..13], &[0x1B, b'@', 0x1B, b'a', 1, 0x1D, b'v', b'0', 0, 8, 0, 58, 0][..]);
+        assert_eq!(escpos.len(), 13 + 8 * 58 + 6);
+        let dots: u32 = escpos[13..13 + 8 * 58].iter().map(|byte| byte.count_ones()).sum();
+        assert_eq!(dots, dark * 4);
+
+        let zpl = to_zpl(&qr, 2, 4).unwrap();
+        assert!(zpl.starts_with("^XA\n^FO0,0^GFA,464,464,8,") && zpl.ends_with("^FS\n^XZ\n"));
+
+        let native = to_zpl_native(b"A^B_C\xff", ECLevel::Q, 4).unwrap();
+        assert!(native.contains("^BQN,2,4^FH^FDQA,A_5EB_5FC_FF^FS"));
+        assert!(to_zpl_native(b"A", ECLevel::Q, 11).is_err());
+
+        // dot sizes are capped, and ESC/POS symbols must fit across the print head
+        assert!(to_zpl(&qr, MAX_DOT_SIZE + 1, 4).is_err());
+        assert!(to_escpos(&qr, 0, 4, DEFAULT_HEAD_DOTS).is_err());
+        let (large, _) = QRConfig::from_data(b"HELLO".to_vec(), Version::new(40).ok(), ECLevel::Low).unwrap().gen_qr_code();
+        assert!(to_escpos(&large, 4, 4, DEFAULT_HEAD_DOTS).is_err());
+        assert!(to_escpos(&large, 4, 4, 740).is_ok());
+    }
+
+    #[test]
+    fn test_sheet_layout() {
+        let layout = "avery-L7160".parse::<SheetLayout>().unwrap();
+        assert_eq!((layout.page, layout.columns, layout.rows), (A4, 3, 7));
+        let grid = "letter:4x5".parse::<SheetLayout>().unwrap();
+        assert!((grid.label_width - (215.9 - 20.0) / 4.0).abs() < 0.001);
+        assert!("a4:0x5".parse::<SheetLayout>().is_err() && "avery-none".parse::<SheetLayout>().is_err());
+
+        let labels: Vec<Label> = (0..25).map(|i| {
+            let (qr, _) = QRConfig::from_data(format!("ASSET-{:04}", i).into_bytes(), None, ECLevel::Medium).unwrap().gen_qr_code();
+            let caption = if i == 24 { "x".repeat(200) } else { format!("Asset <{}>", i) };
+            Label { qr, caption }
+        }).collect();
+
+        let pdf = to_sheet_pdf(&labels, &layout).unwrap();
+        let pdf = String::from_utf8_lossy(&pdf);
+        assert!(pdf.contains("/Count 2 >>") && pdf.contains("(Asset <3>) Tj"));
+
+        let svgs = to_sheet_svgs(&labels, &layout).unwrap();
+        assert_eq!(svgs.len(), 2);
+        assert_eq!(svgs[0].matches("<text").count(), 21);
+        assert!(svgs[0].contains(">Asset &lt;3&gt;</text>") && svgs[1].contains("xxx...</text>"));
+
+        assert!(to_sheet_pdf(&labels, &SheetLayout::grid(A4, 40, 40, 10.0)).is_err());
+        assert!(to_sheet_pdf(&[], &layout).is_err());
+    }
+}
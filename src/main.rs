@@ -1,61 +1,272 @@
-
-pub mod qr_encoder;
-extern crate image as image_lib;
-extern crate reed_solomon;
-
-use qr_encoder::qr::QR;
-use qr_encoder::config::{QRConfig};
-use qr_encoder::cell::{Cell, CellType, Color};
-use qr_encoder::util::{get_pixel_points, args};
+extern crate qr_encode;
 
 use std::fs::File;
+use std::io::{stdout, Write};
 use std::path::Path;
+use std::process;
+
+use qr_encode::qr_encoder::cli::{command, Command, StressArgs, OtpArgs, SheetArgs};
+use qr_encode::qr_encoder::serve::serve;
+use qr_encode::qr_encoder::sheet::{Label, to_sheet_pdf, to_sheet_svgs};
+use qr_encode::qr_encoder::debug::{annotate, to_debug_svg};
+use qr_encode::qr_encoder::logo::logo_area;
+use qr_encode::qr_encoder::raster::{save_qr_image, save_styled_image, save_swiss_image, save_debug_image, create_styled_image, open_logo, overlay_logo};
+use qr_encode::qr_encoder::pdf::{to_pdf, to_swiss_pdf};
+use qr_encode::qr_encoder::printer::{to_zpl, to_zpl_native, to_escpos};
+use qr_encode::qr_encoder::qr::QR;
+use qr_encode::qr_encoder::config::{CellTrack, QRConfig};
+use qr_encode::qr_encoder::error::QRError;
+use qr_encode::qr_encoder::cli::Args;
+use qr_encode::qr_encoder::stress::stress;
+use qr_encode::qr_encoder::svg::{to_svg, to_styled_svg, to_swiss_svg};
+use qr_encode::qr_encoder::payload::Payload;
+use qr_encode::qr_encoder::text::to_text;
+
+// renders the symbol with the logo over its middle to `path`, warning when
+// the logo covers more than error correction can safely recover
+fn save_logo_image(args: &Args, canvas: &QR, tracker: &CellTrack, logo: &Path, path: &Path) -> Result<(), String> {
+    let logo = open_logo(logo).map_err(|err| format!("{}: {}", logo.display(), err))?;
+    let style = args.style.unwrap_or_default();
+    let mut img = create_styled_image(canvas, &style).map_err(|err| err.to_string())?;
+
+    let version = args.config.version;
+    let width = canvas.width() as f32;
+    let requested = args.logo_size.map(|size| (size * width + 0.5) as usize);
+    let area = logo_area(canvas, tracker, version, requested, args.logo_box).map_err(|err| err.to_string())?;
+    if area.logo_size() == 0 && requested.is_some() {
+        eprintln!("warning: the logo size is under a module across, so the logo was left out");
+    } else if area.logo_size() == 0 {
+        eprintln!(
+            "warning: no logo fits within what error correction can safely recover, so it was left out; \
+use a larger version or a smaller --logo-box"
+        );
+    } else if !area.is_safe() {
+        let safe = logo_area(canvas, tracker, version, None, args.logo_box).map_err(|err| err.to_string())?;
+        // rounded down, so the suggestion stays within the budget
+        let fraction = (safe.logo_size() as f32 / width * 100.0).floor() / 100.0;
+        if fraction > 0.0 {
+            eprintln!(
+                "warning: the logo covers {} codewords of one error correction block but only {} a block can be \
+safely recovered; use --logo-size {:.2} or less",
+                area.worst_block, area.budget, fraction
+            );
+        } else {
+            eprintln!(
+                "warning: the logo covers {} codewords of one error correction block but only {} a block can be \
+safely recovered; no logo size is safe, use a larger version or a smaller --logo-box",
+                area.worst_block, area.budget
+            );
+        }
+    }
+
+    overlay_logo(&mut img, canvas, &area, &logo, &style);
+    img.save(path).map_err(|err| err.to_string())
+}
+
+// writes printer commands to the file, or to stdout for `-`
+fn write_commands(path: &Path, commands: &[u8]) -> Result<(), String> {
+    let result = if path == Path::new("-") {
+        let out = stdout();
+        let mut out = out.lock();
+        out.write_all(commands).and_then(|_| out.flush())
+    } else {
+        File::create(path).and_then(|mut file| file.write_all(commands))
+    };
+    result.map_err(|err| format!("{}: {}", path.display(), err))
+}
 
-use self::image_lib::{
-    ImageBuffer,
-    Rgba
-};
+// prints how often the message survived the damage at each error correction level
+fn run_stress(args: &StressArgs) -> Result<(), String> {
+    let results = stress(&args.data, args.version, &args.damages, args.trials, args.seed).map_err(|err| match (err, args.version) {
+        (err @ QRError::DataTooLong { .. }, Some(version)) => {
+            format!("the message doesn't fit version {} at any error correction level: {}", version.number(), err)
+        },
+        (err, _) => err.to_string()
+    })?;
 
+    println!("{:<6} {:>7} {:>12} {:>6} {:>16}", "level", "version", "ec codewords", "blocks", "decoded");
+    for result in results.iter() {
+        println!(
+            "{:<6} {:>7} {:>12} {:>6} {:>9}/{:<4} {:>3.0}%",
+            format!("{:?}", result.err_correction_level), result.version, result.ecc_codewords, result.blocks,
+            result.successes, result.trials, result.success_rate() * 100.0
+        );
+    }
+    Ok(())
+}
 
-fn create_qr_image(qr: &QR, config: &QRConfig) {
-    let dimensions: u32 = (config.size) as u32;
-    let mut img = ImageBuffer::new(dimensions * 28, dimensions * 28);
+// encodes an authenticator enrolment. The symbol carries the secret, so it is
+// only written to the paths asked for, and printed to the terminal otherwise.
+fn run_otp(args: &OtpArgs) -> Result<(), String> {
+    let mut config = args.otp.config(args.version, args.ec_level).map_err(|err| err.to_string())?;
+    let (canvas, _) = config.gen_qr_code();
 
-    for pixel in img.pixels_mut() {
-        let white: Rgba<u8> = Rgba { data: [255, 255, 255, 255] };
-        *pixel = white;
+    if let Some(ref path) = args.output {
+        save_qr_image(&canvas, path).map_err(|err| err.to_string())?;
     }
+    if let Some(ref path) = args.svg {
+        File::create(path)
+            .and_then(|mut file| file.write_all(to_svg(&canvas, 10, 4).as_bytes()))
+            .map_err(|err| err.to_string())?;
+    }
+    if args.output.is_none() && args.svg.is_none() {
+        print!("{}", to_text(&canvas, 4));
+    }
+    Ok(())
+}
 
-    for cell in &qr.body {
-        for pixel in get_pixel_points(&cell) {
-            let (x, y, color) = pixel;
-            // if x % 20 == 0 || y % 20 == 0 {
-                // // cell border
-                // let rgb = Rgba { data: [125, 125, 125, 255] };
-                // img.put_pixel(x, y, rgb);
-            // } else {
-            let rgb = Rgba { data: [color.r as u8, color.g as u8, color.b as u8, 255] };
-            img.put_pixel(x, y, rgb);
-            // }
-        }
+// encodes every payload of the batch and lays them out on pages
+fn run_sheet(args: &SheetArgs) -> Result<(), String> {
+    let mut labels = vec![];
+    for entry in args.entries.iter() {
+        let mut config = QRConfig::from_data(entry.data.clone(), None, args.ec_level)
+            .map_err(|err| format!("line {}: {}", entry.line, err))?;
+        let (qr, _) = config.gen_qr_code();
+        labels.push(Label { qr, caption: entry.caption.clone() });
     }
-    let path = Path::new("qr.png");
-    let ref mut fin = File::create(&path);
-    match fin {
-        Ok(_) => {
-            image_lib::ImageRgba8(img).save(&path);
+
+    let write = |path: &Path, contents: &[u8]| {
+        File::create(path)
+            .and_then(|mut file| file.write_all(contents))
+            .map_err(|err| format!("{}: {}", path.display(), err))
+    };
+    match args.output.extension() {
+        Some(ext) if ext == "svg" => {
+            let pages = to_sheet_svgs(&labels, &args.layout).map_err(|err| err.to_string())?;
+            if pages.len() == 1 {
+                return write(&args.output, pages[0].as_bytes());
+            }
+            // SVG has no pages, so each goes to a numbered file
+            let stem = args.output.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            for (i, page) in pages.iter().enumerate() {
+                write(&args.output.with_file_name(format!("{}-{}.svg", stem, i + 1)), page.as_bytes())?;
+            }
+            Ok(())
         },
-        Err(err) => {
-            panic!("{} ERROR", err);
-        }
+        _ => write(&args.output, &to_sheet_pdf(&labels, &args.layout).map_err(|err| err.to_string())?)
     }
 }
 
 fn main() {
     // parse the command line arguments
-    let mut config: QRConfig = args();
+    let mut args = match command() {
+        Ok(Command::Encode(args)) => *args,
+        Ok(Command::Stress(args)) => {
+            if let Err(err) = run_stress(&args) {
+                eprintln!("error: {}", err);
+                process::exit(1);
+            }
+            return;
+        },
+        Ok(Command::Otp(args)) => {
+            if let Err(err) = run_otp(&args) {
+                eprintln!("error: {}", err);
+                process::exit(1);
+            }
+            return;
+        },
+        Ok(Command::Sheet(args)) => {
+            if let Err(err) = run_sheet(&args) {
+                eprintln!("error: {}", err);
+                process::exit(1);
+            }
+            return;
+        },
+        Ok(Command::Serve(args)) => {
+            if let Err(err) = serve(&args.host, args.port) {
+                eprintln!("error: {}", err);
+                process::exit(1);
+            }
+            return;
+        },
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    };
+    let config = &mut args.config;
     // kick off the encoding process
-    let (canvas, tracker) = config.gen_qr_code();
+    let (canvas, tracker, trace) = if args.trace.is_some() {
+        let (canvas, tracker, trace) = config.gen_qr_code_with_trace();
+        (canvas, tracker, Some(trace))
+    } else {
+        let (canvas, tracker) = config.gen_qr_code();
+        (canvas, tracker, None)
+    };
+    if config.maximise_ec_level {
+        println!("error correction level: {:?}", canvas.err_correction_level);
+    }
+    if let (Some(path), Some(trace)) = (args.trace.as_ref(), trace) {
+        let json = trace.to_json();
+        if path == Path::new("-") {
+            println!("{}", json);
+        } else if let Err(err) = File::create(path).and_then(|mut file| file.write_all(json.as_bytes())) {
+            panic!("{} ERROR", err);
+        }
+    }
+    if let Some(ref path) = args.debug_render {
+        let data_codewords = config.codeword_properties.get_data_codeword_length();
+        let annotations = annotate(&canvas, &tracker, data_codewords);
+        let result = match path.extension() {
+            Some(ext) if ext == "svg" => {
+                File::create(path).and_then(|mut file| file.write_all(to_debug_svg(&canvas, &annotations, 32).as_bytes()))
+            },
+            _ => save_debug_image(&canvas, &annotations, path)
+        };
+        if let Err(err) = result {
+            panic!("{} ERROR", err);
+        }
+    }
+    if let Some(ref path) = args.svg {
+        let style = args.style.unwrap_or_default();
+        let svg = if args.swiss_bill { Ok(to_swiss_svg(&canvas)) } else { to_styled_svg(&canvas, &style) };
+        let result = svg
+            .map_err(|err| err.to_string())
+            .and_then(|svg| File::create(path).and_then(|mut file| file.write_all(svg.as_bytes())).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+    if let Some(ref path) = args.pdf {
+        // QR-bills come out at their printed size, other symbols at half a millimetre a module
+        let pdf = if args.swiss_bill { to_swiss_pdf(&canvas) } else { to_pdf(&canvas, 0.5, 4) };
+        if let Err(err) = File::create(path).and_then(|mut file| file.write_all(&pdf)) {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+    if let Some(ref path) = args.zpl {
+        let zpl = if args.zpl_native {
+            to_zpl_native(&config.message(), canvas.err_correction_level, args.dot_size)
+        } else {
+            to_zpl(&canvas, args.dot_size, 4)
+        };
+        if let Err(err) = zpl.map_err(|err| err.to_string()).and_then(|zpl| write_commands(path, zpl.as_bytes())) {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+    if let Some(ref path) = args.escpos {
+        let escpos = to_escpos(&canvas, args.dot_size, 4, args.head_dots).map_err(|err| err.to_string());
+        if let Err(err) = escpos.and_then(|escpos| write_commands(path, &escpos)) {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
     // generate the image from the encoded data
-    create_qr_image(&canvas, &config);
+    let output = match args.output {
+        Some(ref output) => output,
+        None => return
+    };
+    let result = match (args.logo.as_ref(), args.style) {
+        _ if args.swiss_bill => save_swiss_image(&canvas, args.dpi, output).map_err(|err| err.to_string()),
+        (Some(logo), _) => save_logo_image(&args, &canvas, &tracker, logo, output),
+        (None, Some(ref style)) => save_styled_image(&canvas, style, output).map_err(|err| err.to_string()),
+        (None, None) => save_qr_image(&canvas, output).map_err(|err| err.to_string())
+    };
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,132 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// a growable sequence of bits, written most significant bit first
+#[derive(Debug, Clone, Default)]
+pub struct BitBuffer {
+    bytes: Vec<u8>,
+    len: usize
+}
+
+// what `BitBuffer::terminate` added to fill the data capacity
+#[derive(Debug, Clone, Default)]
+pub struct Padding {
+    pub terminator_bits: usize,
+    pub bit_padding: usize,
+    pub pad_codewords: Vec<u8>
+}
+
+impl BitBuffer {
+    pub fn new() -> BitBuffer {
+        BitBuffer { bytes: vec![], len: 0 }
+    }
+
+    // number of bits written so far
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // appends the lowest `count` bits of `value`, most significant first
+    pub fn append(&mut self, value: u32, count: usize) {
+        debug_assert!(count <= 32);
+        for shift in (0..count).rev() {
+            self.push_bit((value >> shift) & 1 == 1);
+        }
+    }
+
+    pub fn push_bit(&mut self, bit: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            let last = self.bytes.len() - 1;
+            self.bytes[last] |= 0x80 >> (self.len % 8);
+        }
+        self.len += 1;
+    }
+
+    pub fn append_buffer(&mut self, other: &BitBuffer) {
+        for i in 0..other.len {
+            self.push_bit(other.bit(i));
+        }
+    }
+
+    pub fn bit(&self, index: usize) -> bool {
+        (self.bytes[index / 8] >> (7 - index % 8)) & 1 == 1
+    }
+
+    // ends the message and fills the rest of `capacity` bits as the spec lays out:
+    // a terminator of up to 4 zero bits, zero bits up to the next codeword
+    // boundary, then alternating 0xEC and 0x11 pad codewords
+    pub fn terminate(&mut self, capacity: usize) -> Padding {
+        let mut padding = Padding::default();
+        if self.len >= capacity {
+            return padding;
+        }
+
+        padding.terminator_bits = if capacity - self.len < 4 { capacity - self.len } else { 4 };
+        self.append(0, padding.terminator_bits);
+
+        padding.bit_padding = (8 - self.len % 8) % 8;
+        self.append(0, padding.bit_padding);
+
+        let mut swap = false;
+        while self.len < capacity {
+            let pad = if swap { 17u8 } else { 236u8 };
+            self.append(pad as u32, 8);
+            padding.pad_codewords.push(pad);
+            swap = !swap;
+        }
+
+        padding
+    }
+
+    // the bits packed into bytes, with the last byte padded with zero bits
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    // the bits as a string of 0s and 1s
+    pub fn to_bit_string(&self) -> String {
+        (0..self.len).map(|i| if self.bit(i) { '1' } else { '0' }).collect()
+    }
+}
+
+// reads bits back out of packed bytes, most significant bit first
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> BitReader<'a> {
+        BitReader { bytes, position: 0 }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() * 8 - self.position
+    }
+
+    // the next `count` bits as a number, or `None` when there aren't that many left
+    pub fn read(&mut self, count: usize) -> Option<u32> {
+        if count > self.remaining() {
+            return None;
+        }
+
+        let mut value = 0;
+        for _ in 0..count {
+            let bit = (self.bytes[self.position / 8] >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.position += 1;
+        }
+        Some(value)
+    }
+}
@@ -0,0 +1,143 @@
+use alloc::vec::Vec;
+
+use qr_encoder::config::{ECLevel, EncodingMode};
+use qr_encoder::util::{codeword_info, get_content_length};
+use qr_encoder::error::QRError;
+use qr_encoder::segment::{Segment, segments_bit_length};
+use qr_encoder::version::Version;
+
+// every error correction level, ordered from the least to the most recoverable
+pub const EC_LEVELS: [ECLevel; 4] = [ECLevel::Low, ECLevel::Medium, ECLevel::Q, ECLevel::High];
+
+// a version and error correction level that can hold a payload, along with
+// the number of data bits left over once the payload has been written.
+#[derive(Copy, Clone, Debug)]
+pub struct Fit {
+    pub version: usize,
+    pub err_correction_level: ECLevel,
+    pub remaining_bits: usize
+}
+
+// number of data bits (not counting error correction) a symbol can hold
+pub fn data_bit_capacity(version: Version, err_correction_level: &ECLevel) -> usize {
+    codeword_info(version.number(), err_correction_level).get_data_codeword_length() * 8
+}
+
+// number of bits a segment of `char_count` characters occupies, including
+// the mode indicator and the character count indicator
+pub fn segment_bit_length(char_count: usize, mode: &EncodingMode, version: Version) -> usize {
+    let header = 4 + get_content_length(mode.indicator(), version.number());
+    let body = match *mode {
+        EncodingMode::Numeric => {
+            // three digits per 10 bits, with a trailing group of 4 or 7 bits
+            let tail = match char_count % 3 {
+                0 => 0,
+                1 => 4,
+                _ => 7
+            };
+            (char_count / 3) * 10 + tail
+        },
+        EncodingMode::AlphaNumeric => (char_count / 2) * 11 + (char_count % 2) * 6,
+        EncodingMode::Byte => char_count * 8,
+        EncodingMode::Japanese => char_count * 13
+    };
+
+    header + body
+}
+
+// number of characters the payload contributes in a given mode. Kanji
+// characters are taken to be double byte Shift JIS.
+pub fn char_count(data: &[u8], mode: &EncodingMode) -> usize {
+    match *mode {
+        EncodingMode::Japanese => data.len() / 2,
+        _ => data.len()
+    }
+}
+
+pub fn max_characters(version: Version, err_correction_level: &ECLevel, mode: &EncodingMode) -> usize {
+    let count_bits = get_content_length(mode.indicator(), version.number());
+    let available = data_bit_capacity(version, err_correction_level);
+    let header = 4 + count_bits;
+
+    if available < header {
+        return 0;
+    }
+
+    let bits = available - header;
+    let characters = match *mode {
+        EncodingMode::Numeric => {
+            let tail = match bits % 10 {
+                0..=3 => 0,
+                4..=6 => 1,
+                _ => 2
+            };
+            (bits / 10) * 3 + tail
+        },
+        EncodingMode::AlphaNumeric => (bits / 11) * 2 + if bits % 11 >= 6 { 1 } else { 0 },
+        EncodingMode::Byte => bits / 8,
+        EncodingMode::Japanese => bits / 13
+    };
+
+    // the character count indicator also puts a ceiling on the length
+    let count_limit = (1 << count_bits) - 1;
+    if characters > count_limit {
+        count_limit
+    } else {
+        characters
+    }
+}
+
+// every version and error correction level that can hold the payload, from
+// the smallest version up. Within a version the levels run from L to H.
+pub fn fitting_versions(data: &[u8], mode: &EncodingMode) -> Vec<Fit> {
+    let characters = char_count(data, mode);
+    let mut fits: Vec<Fit> = vec![];
+
+    for version in Version::all() {
+        let required = segment_bit_length(characters, mode, version);
+        for level in EC_LEVELS.iter() {
+            if characters > max_characters(version, level, mode) {
+                continue;
+            }
+
+            fits.push(Fit {
+                version: version.number(),
+                err_correction_level: *level,
+                remaining_bits: data_bit_capacity(version, level) - required
+            });
+        }
+    }
+
+    fits
+}
+
+// the smallest version that holds the payload at the given error correction level
+pub fn smallest_version(data: &[u8], mode: &EncodingMode, err_correction_level: &ECLevel) -> Result<Version, QRError> {
+    let characters = char_count(data, mode);
+
+    for version in Version::all() {
+        if max_characters(version, err_correction_level, mode) >= characters {
+            return Ok(version);
+        }
+    }
+
+    Err(QRError::DataTooLong {
+        length: characters,
+        capacity: max_characters(Version::largest(), err_correction_level, mode)
+    })
+}
+
+// the smallest version whose data capacity holds the segments at the given
+// error correction level
+pub fn smallest_version_for_segments(segments: &[Segment], err_correction_level: &ECLevel) -> Result<Version, QRError> {
+    for version in Version::all() {
+        if segments_bit_length(segments, version.number()) <= data_bit_capacity(version, err_correction_level) {
+            return Ok(version);
+        }
+    }
+
+    Err(QRError::SegmentsTooLong {
+        bits: segments_bit_length(segments, 40),
+        capacity: data_bit_capacity(Version::largest(), err_correction_level)
+    })
+}
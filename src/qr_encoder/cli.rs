@@ -0,0 +1,624 @@
+use std::env::{args_os};
+use std::fs::File;
+use std::io::{stdin, Read};
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+
+use qr_encoder::compact::encode_compact;
+use qr_encoder::config::{QRConfig, ECLevel};
+use qr_encoder::error::QRError;
+use qr_encoder::logo::logo_ec_level;
+use qr_encoder::payment::swiss_bill_config;
+use qr_encoder::printer::{MAX_DOT_SIZE, DEFAULT_HEAD_DOTS};
+use qr_encoder::sheet::SheetLayout;
+use qr_encoder::otp::{OtpAuth, OtpAlgorithm, OtpKind, base32_decode, clear_bytes, clear_string};
+use qr_encoder::stress::{Damage, DamageKind, DamageRegion};
+use qr_encoder::style::{Style, Fill, GradientDirection, parse_color};
+use qr_encoder::version::Version;
+
+pub struct Args {
+    pub config: QRConfig,
+    // where to write an annotated rendering of the symbol (.svg or .png)
+    pub debug_render: Option<PathBuf>,
+    // where to write the encoding trace as JSON, `-` for stdout
+    pub trace: Option<PathBuf>,
+    // module shapes, eyes and colours, when any styling flag is given
+    pub style: Option<Style>,
+    // where to also write the symbol as an SVG
+    pub svg: Option<PathBuf>,
+    // an image to put in the middle of the symbol
+    pub logo: Option<PathBuf>,
+    // the logo's width as a share of the symbol's, sized automatically when not set
+    pub logo_size: Option<f32>,
+    // modules of quiet box to clear around the logo
+    pub logo_box: usize,
+    // render as a Swiss QR-bill: 46mm across with the Swiss cross in the middle
+    pub swiss_bill: bool,
+    // where to also write the symbol as a PDF
+    pub pdf: Option<PathBuf>,
+    // printer resolution for renderings with a physical size
+    pub dpi: u32,
+    // where to write the image: the path given with `-o`, or `qr.png` when
+    // no other output is asked for
+    pub output: Option<PathBuf>,
+    // where to write ZPL or ESC/POS printer commands, `-` for stdout
+    pub zpl: Option<PathBuf>,
+    pub escpos: Option<PathBuf>,
+    // have a ZPL printer encode the message with `^BQ` rather than sending the modules
+    pub zpl_native: bool,
+    // printer dots a module
+    pub dot_size: usize,
+    // dots the ESC/POS printer's head prints across
+    pub head_dots: usize
+}
+
+// options for `qr-encode stress`
+pub struct StressArgs {
+    pub data: Vec<u8>,
+    // the version to test at, the smallest that fits each level when not set
+    pub version: Option<Version>,
+    pub damages: Vec<Damage>,
+    pub trials: usize,
+    pub seed: u64
+}
+
+// options for `qr-encode otp`
+pub struct OtpArgs {
+    pub otp: OtpAuth,
+    pub version: Option<Version>,
+    pub ec_level: ECLevel,
+    // nothing is written to disk unless one of these is given; the symbol
+    // is printed to the terminal instead
+    pub output: Option<PathBuf>,
+    pub svg: Option<PathBuf>
+}
+
+// options for `qr-encode serve`
+pub struct ServeArgs {
+    // the address to listen on, only this machine unless given
+    pub host: String,
+    pub port: u16
+}
+
+// one label of a batch: the payload, and the caption printed under it
+pub struct BatchEntry {
+    pub data: Vec<u8>,
+    pub caption: String,
+    // the line of the input it came from, for error messages
+    pub line: usize
+}
+
+// options for `qr-encode sheet`
+pub struct SheetArgs {
+    pub entries: Vec<BatchEntry>,
+    pub layout: SheetLayout,
+    pub ec_level: ECLevel,
+    // a PDF, or SVGs for a `.svg` path, numbered when there is more than one page
+    pub output: PathBuf
+}
+
+pub enum Command {
+    Encode(Box<Args>),
+    Stress(StressArgs),
+    Otp(OtpArgs),
+    Serve(ServeArgs),
+    Sheet(SheetArgs)
+}
+
+// the value following a flag, as a string
+fn flag_value(flag: &str, value: Option<OsString>) -> Result<String, String> {
+    match value {
+        Some(value) => value.into_string()
+            .map_err(|_| format!("the value for {} is not valid unicode", flag)),
+        None => Err(format!("{} needs a value", flag))
+    }
+}
+
+pub fn args() -> Result<Args, String> {
+    /*
+        default options are....
+            if no version, the default version is 14
+            if no error correction level, the default level is M
+
+
+        to do:
+            flag for encoding type - default will be utf-8 (i think?)
+            ???
+
+    */
+    let mut qr_args = args_os().skip(1);
+    let mut version = Version::new(14).unwrap();
+    let mut data: Option<Vec<u8>> = None;
+    let mut ec_level: ECLevel = ECLevel::Medium;
+    let mut ec_given = false;
+    let mut arg = qr_args.next();
+    let mut maximise_ec_level = false;
+    let mut debug_render: Option<PathBuf> = None;
+    let mut trace: Option<PathBuf> = None;
+    let mut style = Style::default();
+    let mut styled = false;
+    let mut gradient_end = None;
+    let mut svg: Option<PathBuf> = None;
+    let mut logo: Option<PathBuf> = None;
+    let mut logo_size: Option<f32> = None;
+    let mut logo_box = 0;
+    let mut invert = false;
+    let mut mirror = false;
+    let mut version_given = false;
+    let mut swiss_bill = false;
+    let mut pdf: Option<PathBuf> = None;
+    let mut dpi = 300;
+    let mut output: Option<PathBuf> = None;
+    let mut compact = false;
+    let mut zpl: Option<PathBuf> = None;
+    let mut escpos: Option<PathBuf> = None;
+    let mut zpl_native = false;
+    let mut dot_size = 4;
+    let mut head_dots = DEFAULT_HEAD_DOTS;
+    let mut head_dots_given = false;
+
+    while arg.is_some() {
+        let value = arg.unwrap();
+        if value == OsStr::new("-v") {
+            version = flag_value("-v", qr_args.next())?
+                .parse::<Version>()
+                .map_err(|err| err.to_string())?;
+            version_given = true;
+        } else if value == OsStr::new("-m") {
+            data = Some(flag_value("-m", qr_args.next())?.into_bytes());
+        } else if value == OsStr::new("-ec") {
+            ec_level = flag_value("-ec", qr_args.next())?
+                .parse::<ECLevel>()
+                .map_err(|err| err.to_string())?;
+            ec_given = true;
+        } else if value == OsStr::new("--compact") {
+            compact = true;
+        } else if value == OsStr::new("-maxec") {
+            maximise_ec_level = true;
+        } else if value == OsStr::new("--debug-render") {
+            debug_render = Some(PathBuf::from(flag_value("--debug-render", qr_args.next())?));
+        } else if value == OsStr::new("--trace") {
+            trace = Some(PathBuf::from(flag_value("--trace", qr_args.next())?));
+        } else if value == OsStr::new("-DEBUG") {
+            trace = Some(PathBuf::from("-"));
+        } else if value == OsStr::new("--invert") {
+            invert = true;
+        } else if value == OsStr::new("--mirror") {
+            mirror = true;
+        } else if value == OsStr::new("--svg") {
+            svg = Some(PathBuf::from(flag_value("--svg", qr_args.next())?));
+        } else if value == OsStr::new("-o") {
+            output = Some(PathBuf::from(flag_value("-o", qr_args.next())?));
+        } else if value == OsStr::new("--pdf") {
+            pdf = Some(PathBuf::from(flag_value("--pdf", qr_args.next())?));
+        } else if value == OsStr::new("--zpl") {
+            zpl = Some(PathBuf::from(flag_value("--zpl", qr_args.next())?));
+        } else if value == OsStr::new("--zpl-native") {
+            zpl_native = true;
+        } else if value == OsStr::new("--escpos") {
+            escpos = Some(PathBuf::from(flag_value("--escpos", qr_args.next())?));
+        } else if value == OsStr::new("--dot-size") {
+            let dots = flag_value("--dot-size", qr_args.next())?;
+            dot_size = match dots.parse::<usize>() {
+                Ok(dots) if (1..=MAX_DOT_SIZE).contains(&dots) => dots,
+                _ => return Err(format!("dot size {:?} is not a number from 1 to {}", dots, MAX_DOT_SIZE))
+            };
+        } else if value == OsStr::new("--head-dots") {
+            let dots = flag_value("--head-dots", qr_args.next())?;
+            head_dots = match dots.parse::<usize>() {
+                Ok(dots) if dots > 0 => dots,
+                _ => return Err(format!("head width {:?} is not a positive number of dots", dots))
+            };
+            head_dots_given = true;
+        } else if value == OsStr::new("--swiss-bill") {
+            swiss_bill = true;
+        } else if value == OsStr::new("--dpi") {
+            let number = flag_value("--dpi", qr_args.next())?;
+            dpi = match number.parse::<u32>() {
+                Ok(dpi) if dpi > 0 => dpi,
+                _ => return Err(format!("dpi {:?} is not a positive number", number))
+            };
+        } else if value == OsStr::new("--logo") {
+            logo = Some(PathBuf::from(flag_value("--logo", qr_args.next())?));
+        } else if value == OsStr::new("--logo-size") {
+            let size = flag_value("--logo-size", qr_args.next())?;
+            logo_size = match size.parse::<f32>() {
+                Ok(size) if size > 0.0 && size < 1.0 => Some(size),
+                _ => return Err(format!("logo size {:?} is not a fraction between 0 and 1", size))
+            };
+        } else if value == OsStr::new("--logo-box") {
+            let modules = flag_value("--logo-box", qr_args.next())?;
+            logo_box = modules.parse::<usize>()
+                .map_err(|_| format!("logo box {:?} is not a number of modules", modules))?;
+        } else if value == OsStr::new("--shape") {
+            style.shape = flag_value("--shape", qr_args.next())?
+                .parse()
+                .map_err(|err: QRError| err.to_string())?;
+            styled = true;
+        } else if value == OsStr::new("--eyes") {
+            // either one shape for both parts of the eye, or `outer,inner`
+            let shapes = flag_value("--eyes", qr_args.next())?;
+            let mut parts = shapes.splitn(2, ',');
+            style.eyes.outer = parts.next().unwrap_or("").parse().map_err(|err: QRError| err.to_string())?;
+            style.eyes.inner = match parts.next() {
+                Some(inner) => inner.parse().map_err(|err: QRError| err.to_string())?,
+                None => style.eyes.outer
+            };
+            styled = true;
+        } else if value == OsStr::new("--color") {
+            let color = parse_color(&flag_value("--color", qr_args.next())?).map_err(|err| err.to_string())?;
+            style.fill = Fill::Solid(color);
+            styled = true;
+        } else if value == OsStr::new("--gradient") {
+            gradient_end = Some(parse_color(&flag_value("--gradient", qr_args.next())?).map_err(|err| err.to_string())?);
+            styled = true;
+        } else if value == OsStr::new("--eye-color") {
+            let color = parse_color(&flag_value("--eye-color", qr_args.next())?).map_err(|err| err.to_string())?;
+            style.eyes.outer_color = Some(color);
+            style.eyes.inner_color = Some(color);
+            styled = true;
+        } else if value == OsStr::new("--background") {
+            style.background = parse_color(&flag_value("--background", qr_args.next())?).map_err(|err| err.to_string())?;
+            styled = true;
+        } else {
+            return Err(format!("unknown option {:?}", value));
+        }
+
+        arg = qr_args.next();
+    }
+
+    let data = match data {
+        Some(data) => data,
+        None => return Err(String::from("a message is required: -m <MESSAGE>"))
+    };
+
+    if zpl_native && zpl.is_none() {
+        return Err(String::from("--zpl-native needs --zpl to write the label to"));
+    }
+    if head_dots_given && escpos.is_none() {
+        return Err(String::from("--head-dots only applies to --escpos"));
+    }
+
+    if swiss_bill {
+        // the bill's layout is fixed, so nothing that changes the symbol's look or level applies
+        let conflicts = [
+            (logo.is_some(), "--logo"), (styled, "styling options"), (compact, "--compact"),
+            (maximise_ec_level, "-maxec"), (invert, "--invert"), (mirror, "--mirror"),
+            (ec_given && ec_level != ECLevel::Medium, "an error correction level other than M")
+        ];
+        if let Some(&(_, flag)) = conflicts.iter().find(|&&(set, _)| set) {
+            return Err(format!("--swiss-bill can't be combined with {}", flag));
+        }
+    }
+
+    // an image is only written unasked when it would otherwise produce nothing
+    let other_outputs = [&debug_render, &trace, &svg, &pdf, &zpl, &escpos];
+    if output.is_none() && other_outputs.iter().all(|path| path.is_none()) {
+        output = Some(PathBuf::from("qr.png"));
+    }
+
+    if logo.is_some() {
+        ec_level = logo_ec_level(ec_level);
+    }
+
+    // QR-bills are always level M, in the smallest version that fits
+    // unless one was asked for
+    let version = if swiss_bill && !version_given { None } else { Some(version) };
+
+    // zlib and Base45 in an alphanumeric segment, read back with `compact::expand`
+    let config = if swiss_bill {
+        swiss_bill_config(&data, version)
+    } else if compact {
+        encode_compact(&data, version, ec_level)
+    } else {
+        QRConfig::from_data(data, version, ec_level)
+    };
+    let mut config = config.map_err(|err| err.to_string())?;
+    config.maximise_ec_level = maximise_ec_level;
+    config.invert = invert;
+    config.mirror = mirror;
+
+    if let (Some(to), Fill::Solid(from)) = (gradient_end, style.fill) {
+        style.fill = Fill::Linear { from, to, direction: GradientDirection::Diagonal };
+    }
+
+    Ok(Args {
+        config,
+        debug_render,
+        trace,
+        style: if styled { Some(style) } else { None },
+        svg,
+        logo,
+        logo_size,
+        logo_box,
+        swiss_bill,
+        pdf,
+        dpi,
+        output,
+        zpl,
+        escpos,
+        zpl_native,
+        dot_size,
+        head_dots
+    })
+}
+
+// the command to run: `stress` as the first argument runs the damage
+// simulation, `otp` makes an authenticator enrolment, `serve` starts the
+// HTTP service, `sheet` lays out a batch of labels, anything else encodes a
+// symbol
+pub fn command() -> Result<Command, String> {
+    match args_os().nth(1) {
+        Some(ref first) if first == OsStr::new("stress") => stress_args().map(Command::Stress),
+        Some(ref first) if first == OsStr::new("otp") => otp_args().map(Command::Otp),
+        Some(ref first) if first == OsStr::new("serve") => serve_args().map(Command::Serve),
+        Some(ref first) if first == OsStr::new("sheet") => sheet_args().map(Command::Sheet),
+        _ => args().map(|args| Command::Encode(Box::new(args)))
+    }
+}
+
+fn damage(flag: &str, kind: DamageKind, value: Option<OsString>) -> Result<Damage, String> {
+    let region = flag_value(flag, value)?
+        .parse::<DamageRegion>()
+        .map_err(|err| err.to_string())?;
+    Ok(Damage { kind, region })
+}
+
+pub fn stress_args() -> Result<StressArgs, String> {
+    let mut qr_args = args_os().skip(2);
+    let mut data: Option<Vec<u8>> = None;
+    let mut version: Option<Version> = None;
+    let mut damages: Vec<Damage> = vec![];
+    let mut trials = 100;
+    let mut seed = 1;
+    let mut arg = qr_args.next();
+
+    while arg.is_some() {
+        let value = arg.unwrap();
+        if value == OsStr::new("-m") {
+            data = Some(flag_value("-m", qr_args.next())?.into_bytes());
+        } else if value == OsStr::new("-v") {
+            version = Some(flag_value("-v", qr_args.next())?
+                .parse::<Version>()
+                .map_err(|err| err.to_string())?);
+        } else if value == OsStr::new("--flip") {
+            damages.push(damage("--flip", DamageKind::Flip, qr_args.next())?);
+        } else if value == OsStr::new("--erase") {
+            damages.push(damage("--erase", DamageKind::Erase, qr_args.next())?);
+        } else if value == OsStr::new("--trials") {
+            let count = flag_value("--trials", qr_args.next())?;
+            trials = count.parse::<usize>()
+                .map_err(|_| format!("trials {:?} is not a number", count))?;
+        } else if value == OsStr::new("--seed") {
+            let number = flag_value("--seed", qr_args.next())?;
+            seed = number.parse::<u64>()
+                .map_err(|_| format!("seed {:?} is not a number", number))?;
+        } else {
+            return Err(format!("unknown option {:?}", value));
+        }
+
+        arg = qr_args.next();
+    }
+
+    let data = match data {
+        Some(data) => data,
+        None => return Err(String::from("a message is required: -m <MESSAGE>"))
+    };
+    if damages.is_empty() {
+        return Err(String::from("no damage to apply: use --flip or --erase with random:N, burst:N or rect:ROW,COL,WIDTH,HEIGHT"));
+    }
+
+    Ok(StressArgs { data, version, damages, trials, seed })
+}
+
+// decodes a hex secret, clearing the digits it copies along the way
+fn hex_decode(hex: &str) -> Result<Vec<u8>, String> {
+    let mut digits: Vec<u8> = Vec::with_capacity(hex.len());
+    digits.extend(hex.bytes().filter(|b| !b.is_ascii_whitespace()));
+    let mut secret = Vec::with_capacity(digits.len() / 2);
+    let mut valid = digits.len().is_multiple_of(2);
+    for pair in digits.chunks(2) {
+        match (char::from(pair[0]).to_digit(16), pair.get(1).and_then(|&b| char::from(b).to_digit(16))) {
+            (Some(high), Some(low)) if valid => secret.push((high << 4 | low) as u8),
+            _ => valid = false
+        }
+    }
+    clear_bytes(&mut digits);
+    if !valid {
+        clear_bytes(&mut secret);
+        return Err(String::from("the hex secret is not an even number of hex digits"));
+    }
+    Ok(secret)
+}
+
+pub fn otp_args() -> Result<OtpArgs, String> {
+    let mut qr_args = args_os().skip(2);
+    // the secret goes straight into `otp`, which clears it when dropped,
+    // even when a later argument is rejected
+    let mut otp = OtpAuth::totp("", "", &[]);
+    let mut secret_given = false;
+    let mut version: Option<Version> = None;
+    let mut ec_level = ECLevel::Medium;
+    let mut output: Option<PathBuf> = None;
+    let mut svg: Option<PathBuf> = None;
+    let mut arg = qr_args.next();
+
+    while arg.is_some() {
+        let value = arg.unwrap();
+        if value == OsStr::new("--issuer") {
+            otp.issuer = flag_value("--issuer", qr_args.next())?;
+        } else if value == OsStr::new("--account") {
+            otp.account = flag_value("--account", qr_args.next())?;
+        } else if value == OsStr::new("--secret") {
+            // `-` reads the secret from stdin, keeping it out of the process list
+            let mut encoded = flag_value("--secret", qr_args.next())?;
+            if encoded == "-" {
+                // room for any sensible secret, so reading doesn't reallocate
+                encoded = String::with_capacity(1024);
+                stdin().read_line(&mut encoded).map_err(|err| err.to_string())?;
+            }
+            let decoded = base32_decode(&encoded).map_err(|err| err.to_string());
+            clear_string(&mut encoded);
+            clear_bytes(&mut otp.secret);
+            otp.secret = decoded?;
+            secret_given = true;
+        } else if value == OsStr::new("--secret-hex") {
+            let mut hex = flag_value("--secret-hex", qr_args.next())?;
+            let decoded = hex_decode(&hex);
+            clear_string(&mut hex);
+            clear_bytes(&mut otp.secret);
+            otp.secret = decoded?;
+            secret_given = true;
+        } else if value == OsStr::new("--hotp") {
+            let counter = flag_value("--hotp", qr_args.next())?;
+            otp.kind = OtpKind::Hotp {
+                counter: counter.parse::<u64>().map_err(|_| format!("counter {:?} is not a number", counter))?
+            };
+        } else if value == OsStr::new("--period") {
+            let period = flag_value("--period", qr_args.next())?;
+            otp.kind = OtpKind::Totp {
+                period: period.parse::<u32>().map_err(|_| format!("period {:?} is not a number of seconds", period))?
+            };
+        } else if value == OsStr::new("--digits") {
+            let digits = flag_value("--digits", qr_args.next())?;
+            otp.digits = digits.parse::<u32>().map_err(|_| format!("digits {:?} is not a number", digits))?;
+        } else if value == OsStr::new("--algorithm") {
+            otp.algorithm = match flag_value("--algorithm", qr_args.next())?.to_lowercase().as_str() {
+                "sha1" => OtpAlgorithm::Sha1,
+                "sha256" => OtpAlgorithm::Sha256,
+                "sha512" => OtpAlgorithm::Sha512,
+                other => return Err(format!("unknown algorithm {:?}, expected sha1, sha256 or sha512", other))
+            };
+        } else if value == OsStr::new("-v") {
+            version = Some(flag_value("-v", qr_args.next())?
+                .parse::<Version>()
+                .map_err(|err| err.to_string())?);
+        } else if value == OsStr::new("-ec") {
+            ec_level = flag_value("-ec", qr_args.next())?
+                .parse::<ECLevel>()
+                .map_err(|err| err.to_string())?;
+        } else if value == OsStr::new("-o") {
+            output = Some(PathBuf::from(flag_value("-o", qr_args.next())?));
+        } else if value == OsStr::new("--svg") {
+            svg = Some(PathBuf::from(flag_value("--svg", qr_args.next())?));
+        } else {
+            return Err(format!("unknown option {:?}", value));
+        }
+
+        arg = qr_args.next();
+    }
+
+    if !secret_given {
+        return Err(String::from("a secret is required: --secret <BASE32> or --secret-hex <HEX>"));
+    }
+
+    Ok(OtpArgs { otp, version, ec_level, output, svg })
+}
+
+pub fn serve_args() -> Result<ServeArgs, String> {
+    let mut qr_args = args_os().skip(2);
+    let mut host = String::from("127.0.0.1");
+    let mut port = 8080;
+    let mut arg = qr_args.next();
+
+    while arg.is_some() {
+        let value = arg.unwrap();
+        if value == OsStr::new("--port") {
+            let number = flag_value("--port", qr_args.next())?;
+            port = number.parse::<u16>()
+                .map_err(|_| format!("port {:?} is not a number from 0 to 65535", number))?;
+        } else if value == OsStr::new("--host") {
+            host = flag_value("--host", qr_args.next())?;
+        } else {
+            return Err(format!("unknown option {:?}", value));
+        }
+
+        arg = qr_args.next();
+    }
+
+    Ok(ServeArgs { host, port })
+}
+
+// a batch of payloads, one a line. A tab separates a payload from the caption
+// to print under it, which is otherwise the payload itself; blank lines are
+// skipped.
+pub fn read_batch<R: Read>(mut input: R) -> Result<Vec<BatchEntry>, String> {
+    let mut text = String::new();
+    input.read_to_string(&mut text).map_err(|err| err.to_string())?;
+
+    let mut entries = vec![];
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut parts = line.splitn(2, '\t');
+        let data = parts.next().unwrap_or("");
+        let caption = parts.next().unwrap_or(data);
+        entries.push(BatchEntry { data: data.as_bytes().to_vec(), caption: String::from(caption), line: i + 1 });
+    }
+    Ok(entries)
+}
+
+pub fn sheet_args() -> Result<SheetArgs, String> {
+    let mut qr_args = args_os().skip(2);
+    let mut input: Option<PathBuf> = None;
+    let mut layout_spec = String::from("a4:3x8");
+    let mut margin: Option<f32> = None;
+    let mut captions = true;
+    let mut cut_marks = false;
+    let mut ec_level = ECLevel::Medium;
+    let mut output = PathBuf::from("sheet.pdf");
+    let mut arg = qr_args.next();
+
+    while arg.is_some() {
+        let value = arg.unwrap();
+        if value == OsStr::new("--input") {
+            input = Some(PathBuf::from(flag_value("--input", qr_args.next())?));
+        } else if value == OsStr::new("--layout") {
+            layout_spec = flag_value("--layout", qr_args.next())?;
+        } else if value == OsStr::new("--margin") {
+            let millimetres = flag_value("--margin", qr_args.next())?;
+            margin = match millimetres.parse::<f32>() {
+                Ok(margin) if margin >= 0.0 => Some(margin),
+                _ => return Err(format!("margin {:?} is not a number of millimetres", millimetres))
+            };
+        } else if value == OsStr::new("--no-captions") {
+            captions = false;
+        } else if value == OsStr::new("--cut-marks") {
+            cut_marks = true;
+        } else if value == OsStr::new("-ec") {
+            ec_level = flag_value("-ec", qr_args.next())?
+                .parse::<ECLevel>()
+                .map_err(|err| err.to_string())?;
+        } else if value == OsStr::new("-o") {
+            output = PathBuf::from(flag_value("-o", qr_args.next())?);
+        } else {
+            return Err(format!("unknown option {:?}", value));
+        }
+
+        arg = qr_args.next();
+    }
+
+    let mut layout = layout_spec.parse::<SheetLayout>().map_err(|err| err.to_string())?;
+    if let Some(margin) = margin {
+        // Avery sheets come with their labels already placed
+        if layout_spec.to_lowercase().starts_with("avery-") {
+            return Err(String::from("--margin only applies to a4: and letter: grids"));
+        }
+        layout = SheetLayout::grid(layout.page, layout.columns, layout.rows, margin);
+    }
+    layout.captions = captions;
+    layout.cut_marks = cut_marks;
+
+    // `-` reads the batch from stdin
+    let entries = match input {
+        Some(ref path) if path.as_os_str() == "-" => read_batch(stdin())?,
+        Some(ref path) => read_batch(File::open(path).map_err(|err| format!("{}: {}", path.display(), err))?)?,
+        None => return Err(String::from("a batch of payloads is required: --input <FILE>, one a line"))
+    };
+    if entries.is_empty() {
+        return Err(String::from("the batch has no payloads"));
+    }
+
+    Ok(SheetArgs { entries, layout, ec_level, output })
+}
@@ -0,0 +1,94 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "compression")]
+use miniz_oxide::deflate::compress_to_vec_zlib;
+#[cfg(feature = "compression")]
+use miniz_oxide::inflate::{decompress_to_vec_zlib_with_limit, TINFLStatus};
+
+#[cfg(feature = "compression")]
+use qr_encoder::config::{QRConfig, ECLevel};
+use qr_encoder::error::QRError;
+#[cfg(feature = "compression")]
+use qr_encoder::segment::Segment;
+use qr_encoder::segment::{ALPHANUMERIC_CHARSET, alphanumeric_value};
+#[cfg(feature = "compression")]
+use qr_encoder::version::Version;
+
+// the best zlib compression level; payloads are small so speed doesn't matter
+#[cfg(feature = "compression")]
+const COMPRESSION_LEVEL: u8 = 9;
+
+// the most `expand` inflates to. A symbol carries a few kilobytes at most, so
+// anything larger is a zlib bomb rather than a real payload.
+#[cfg(feature = "compression")]
+pub const MAX_EXPANDED_BYTES: usize = 1024 * 1024;
+
+// RFC 9285 Base45, whose 45 characters are exactly the alphanumeric mode's:
+// each pair of bytes becomes three characters, least significant first, and
+// a trailing byte two
+pub fn base45_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(2) * 3);
+    for chunk in data.chunks(2) {
+        let (mut value, digits) = match *chunk {
+            [high, low] => ((high as usize) << 8 | low as usize, 3),
+            _ => (chunk[0] as usize, 2)
+        };
+        for _ in 0..digits {
+            encoded.push(ALPHANUMERIC_CHARSET[value % 45] as char);
+            value /= 45;
+        }
+    }
+    encoded
+}
+
+pub fn base45_decode(encoded: &[u8]) -> Result<Vec<u8>, QRError> {
+    let invalid = || QRError::Undecodable(String::from("the data is not valid Base45"));
+    if encoded.len() % 3 == 1 {
+        return Err(invalid());
+    }
+
+    let mut data = Vec::with_capacity(encoded.len() / 3 * 2 + 1);
+    for chunk in encoded.chunks(3) {
+        let mut value = 0;
+        for c in chunk.iter().rev() {
+            value = value * 45 + alphanumeric_value(*c).ok_or_else(invalid)? as usize;
+        }
+        if chunk.len() == 3 {
+            if value > 0xFFFF {
+                return Err(invalid());
+            }
+            data.push((value >> 8) as u8);
+            data.push(value as u8);
+        } else {
+            if value > 0xFF {
+                return Err(invalid());
+            }
+            data.push(value as u8);
+        }
+    }
+    Ok(data)
+}
+
+// zlib compresses the data and Base45 encodes it, the way the EU digital
+// COVID certificate carries CBOR in a symbol
+#[cfg(feature = "compression")]
+pub fn compact(data: &[u8]) -> String {
+    base45_encode(&compress_to_vec_zlib(data, COMPRESSION_LEVEL))
+}
+
+// undoes `compact`, taking the message read from a symbol
+#[cfg(feature = "compression")]
+pub fn expand(encoded: &[u8]) -> Result<Vec<u8>, QRError> {
+    decompress_to_vec_zlib_with_limit(&base45_decode(encoded)?, MAX_EXPANDED_BYTES).map_err(|err| match err.status {
+        TINFLStatus::HasMoreOutput => QRError::Undecodable(format!("the data expands to more than {} bytes", MAX_EXPANDED_BYTES)),
+        _ => QRError::Undecodable(String::from("the data doesn't decompress"))
+    })
+}
+
+// a config for the compacted data in a single alphanumeric segment
+#[cfg(feature = "compression")]
+pub fn encode_compact(data: &[u8], version: Option<Version>, err_correction_level: ECLevel) -> Result<QRConfig, QRError> {
+    let segment = Segment::AlphaNumeric(compact(data).into_bytes());
+    QRConfig::from_segments(vec![segment], version, err_correction_level)
+}
@@ -1,7 +1,9 @@
 extern crate reed_solomon;
 
-use std::ops::{BitXorAssign};
-use std::fmt::{Binary, Debug};
+use alloc::vec::Vec;
+use alloc::boxed::Box;
+use alloc::str::FromStr;
+use alloc::string::ToString;
 use self::reed_solomon::{Encoder, Buffer};
 
 use qr_encoder::cell::{
@@ -12,9 +14,16 @@ use qr_encoder::cell::{
     PlotPoint
 };
 use qr_encoder::qr::QR;
-use qr_encoder::util::{CodeWord, get_indices_for_dimensions};
+use qr_encoder::util::{CodeWord, codeword_info, square_count, get_indices_for_dimensions, get_content_length, alignment_pattern_centers};
+use qr_encoder::capacity::{EC_LEVELS, char_count, max_characters, smallest_version, smallest_version_for_segments, data_bit_capacity};
+use qr_encoder::bits::BitBuffer;
+use qr_encoder::segment::{Segment, segments_bit_length};
+use qr_encoder::error::QRError;
+use qr_encoder::version::Version;
+use qr_encoder::trace::{Trace, SegmentTrace, BlockTrace, MaskPenalty};
 
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum ECLevel {
     Low,
     Medium,
@@ -22,6 +31,22 @@ pub enum ECLevel {
     High,
 }
 
+impl FromStr for ECLevel {
+    type Err = QRError;
+
+    // accepts the level's letter in either case
+    fn from_str(level: &str) -> Result<ECLevel, QRError> {
+        match level.trim() {
+            "l" | "L" => Ok(ECLevel::Low),
+            "m" | "M" => Ok(ECLevel::Medium),
+            "q" | "Q" => Ok(ECLevel::Q),
+            "h" | "H" => Ok(ECLevel::High),
+            _ => Err(QRError::InvalidECLevel(level.to_string()))
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum EncodingMode {
     Numeric,
     AlphaNumeric,
@@ -29,6 +54,18 @@ pub enum EncodingMode {
     Japanese
 }
 
+impl EncodingMode {
+    // the 4 bit mode indicator that precedes each segment
+    pub fn indicator(&self) -> u8 {
+        match *self {
+            EncodingMode::Numeric => 1,
+            EncodingMode::AlphaNumeric => 2,
+            EncodingMode::Byte => 4,
+            EncodingMode::Japanese => 8
+        }
+    }
+}
+
 pub struct QRConfig {
     pub version: usize,
     pub data: Vec<u8>,
@@ -37,11 +74,25 @@ pub struct QRConfig {
     pub mask: usize,
     pub encoding: u8, // for now - should be its own sub-type.
     pub encoding_mode: EncodingMode,
-    pub debug_mode: bool,
+    // the message as a list of segments. When empty, `data` is written as a
+    // single segment in `encoding_mode`.
+    pub segments: Vec<Segment>,
+    // when set, each step of the encoding is recorded here
+    pub trace: Option<Trace>,
     pub requires_alignment: bool,
     pub finder_points: [(usize, usize); 3],
     pub size: usize,
-    pub err_correction_level: ECLevel
+    pub err_correction_level: ECLevel,
+    // when set, the error correction level is raised to the highest level
+    // the data still fits in for the chosen version
+    pub maximise_ec_level: bool,
+    // when set, this mask pattern is applied instead of the one with the
+    // lowest penalty
+    pub fixed_mask: Option<usize>,
+    // light modules on a dark background, quiet zone included
+    pub invert: bool,
+    // flipped left to right, for reading through glass
+    pub mirror: bool
 }
 
 const ECC_FORMAT_MASK: u16 = 21522;
@@ -69,7 +120,7 @@ fn assign_bit_from_codeword(index: usize, body: &mut Vec<Cell>, dark: bool) -> i
     }
 }
 
-fn zig_zag_points(canvas_size: usize) -> Vec<usize> {
+pub fn zig_zag_points(canvas_size: usize) -> Vec<usize> {
     let mut col = canvas_size - 1;
     let mut row = canvas_size - 1;
     let mut indices = vec![];
@@ -130,10 +181,9 @@ fn interleave_blocks(blocks: &[Buffer], block_size: usize, ecc_block_size: usize
     let mut data: Vec<u8> = vec![];
     for i in 0..block_size {
         for block in blocks {
+            // blocks in the first group are one codeword shorter than those in the second
             if let Some(cw) = block.data().get(i) {
                 data.push(*cw);
-            } else {
-                println!("bleh: {}, block_size: {}", i, block_size);
             }
         }
     }
@@ -153,24 +203,162 @@ fn interleave_blocks(blocks: &[Buffer], block_size: usize, ecc_block_size: usize
 
 pub struct CellTrack(pub Vec<usize>, pub Vec<usize>, pub Vec<usize>);
 
-impl QRConfig {    
+impl QRConfig {
+    pub fn new(version: Version, data: Vec<u8>, err_correction_level: ECLevel) -> QRConfig {
+        let version = version.number();
+        QRConfig {
+            version,
+            data,
+            codewords: vec![],
+            codeword_properties: codeword_info(version, &err_correction_level),
+            mask: 1,
+            encoding: 4u8,
+            encoding_mode: EncodingMode::Byte,
+            segments: vec![],
+            trace: None,
+            requires_alignment: version > 1,
+            err_correction_level,
+            size: square_count(version),
+            finder_points: [
+                (0, 0),
+                ((square_count(version) - 7), 0),
+                (0, (square_count(version) - 7))
+            ],
+            maximise_ec_level: false,
+            fixed_mask: None,
+            invert: false,
+            mirror: false
+        }
+    }
+
+    // checks that the data fits, picking the smallest version that holds it
+    // when no version is given
+    pub fn from_data(data: Vec<u8>, version: Option<Version>, err_correction_level: ECLevel) -> Result<QRConfig, QRError> {
+        let mode = EncodingMode::Byte;
+        let version = match version {
+            Some(v) => v,
+            None => smallest_version(&data, &mode, &err_correction_level)?
+        };
+
+        let capacity = max_characters(version, &err_correction_level, &mode);
+        let length = char_count(&data, &mode);
+        if length > capacity {
+            return Err(QRError::DataTooLong { length, capacity });
+        }
+
+        Ok(QRConfig::new(version, data, err_correction_level))
+    }
+
+    // a config for a message made up of several segments, possibly in
+    // different modes, checking the data bits fit
+    pub fn from_segments(segments: Vec<Segment>, version: Option<Version>, err_correction_level: ECLevel) -> Result<QRConfig, QRError> {
+        let version = match version {
+            Some(v) => v,
+            None => smallest_version_for_segments(&segments, &err_correction_level)?
+        };
+
+        let bits = segments_bit_length(&segments, version.number());
+        let capacity = data_bit_capacity(version, &err_correction_level);
+        if bits > capacity {
+            return Err(QRError::SegmentsTooLong { bits, capacity });
+        }
+
+        let mut config = QRConfig::new(version, vec![], err_correction_level);
+        config.segments = segments;
+        Ok(config)
+    }
+
+    // the segments that make up the message
+    pub fn message_segments(&self) -> Vec<Segment> {
+        if !self.segments.is_empty() {
+            return self.segments.clone();
+        }
+
+        let segment = Segment::with_mode(self.encoding_mode, &self.data)
+            .unwrap_or_else(|_| Segment::byte(&self.data));
+        vec![segment]
+    }
+
+    // the message the segments carry, without their modes
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = vec![];
+        for segment in self.message_segments() {
+            match segment {
+                Segment::Numeric(data) | Segment::AlphaNumeric(data) | Segment::Byte(data) | Segment::Kanji(data) => {
+                    message.extend(data);
+                },
+                Segment::Eci(_) => {}
+            }
+        }
+        message
+    }
+
+    // like `gen_qr_code`, but also returns a record of every encoding step
+    pub fn gen_qr_code_with_trace(&mut self) -> (QR, CellTrack, Trace) {
+        self.trace = Some(Trace::default());
+        let (canvas, tracker) = self.gen_qr_code();
+        let trace = self.trace.take().unwrap_or_default();
+
+        (canvas, tracker, trace)
+    }
+
     pub fn gen_qr_code(&mut self) -> (QR, CellTrack) {
+        if self.maximise_ec_level {
+            self.apply_max_ec_level();
+        }
+
+        if let Some(ref mut trace) = self.trace {
+            trace.version = self.version;
+            trace.err_correction_level = Some(self.err_correction_level);
+        }
+
         self.translate_data();
         self.encode_error_correction_codewords();
 
-        let mut canvas: QR = QR {
-            body: self.create_body()
-        };
+        let mut canvas: QR = QR::new(self.create_body(), self.err_correction_level);
         
         canvas.setup(&self);
         
         let tracker = self.process_data(&mut canvas);
         self.post_process_data(&mut canvas);
-        
+
+        if self.invert {
+            canvas.invert();
+        }
+        if self.mirror {
+            canvas.mirror();
+        }
+
         (canvas, tracker)
     }
 
-    fn process_data(&self, canvas: &mut QR) -> CellTrack {
+    // the highest error correction level, no lower than the one already chosen,
+    // whose data capacity at this version still holds the data. A config
+    // whose version is outside 1 to 40 keeps its level.
+    pub fn max_ec_level(&self) -> ECLevel {
+        let mut chosen = self.err_correction_level;
+        let version = match Version::new(self.version) {
+            Ok(version) => version,
+            Err(_) => return chosen
+        };
+        let bits = segments_bit_length(&self.message_segments(), self.version);
+
+        for level in EC_LEVELS.iter().skip_while(|level| **level != self.err_correction_level) {
+            if data_bit_capacity(version, level) >= bits {
+                chosen = *level;
+            }
+        }
+
+        chosen
+    }
+
+    pub fn apply_max_ec_level(&mut self) {
+        let level = self.max_ec_level();
+        self.err_correction_level = level;
+        self.codeword_properties = codeword_info(self.version, &level);
+    }
+
+    fn process_data(&mut self, canvas: &mut QR) -> CellTrack {
         let mut bit_index = 7;
         let mut codeword_index = 0usize;
         let mut bit_order = vec![];
@@ -195,6 +383,9 @@ impl QRConfig {
         }
 
         let mut remainder_bits = self.get_remainder_bit_length();
+        if let Some(ref mut trace) = self.trace {
+            trace.remainder_bits = remainder_bits as usize;
+        }
         while remainder_bits > 0 {
             let i = pathing_iter.next().unwrap();
             remainder_bits += assign_bit_from_codeword(*i, &mut canvas.body, false);
@@ -203,19 +394,29 @@ impl QRConfig {
         CellTrack(bit_order, cw_order, point_order)
     }
     
-    fn post_process_data(&self, canvas: &mut QR) {
+    fn post_process_data(&mut self, canvas: &mut QR) {
         let body = &mut canvas.body;
         let mut best = 0;
         let mut best_pattern = 0;
-        for pattern in 0..7 {
-            let mut copy = &mut body.clone();
-            self.apply_mask_pattern(&mut copy, pattern);
-            let score = self.eval_penalty_scores(copy);
+        let mut penalties: Vec<MaskPenalty> = vec![];
+        for pattern in 0..8 {
+            // each candidate is scored as it would be printed, format and
+            // version information included
+            let copy = &mut body.clone();
+            self.apply_mask_pattern(copy, pattern);
+            self.encode_format_areas(copy, pattern as u8);
+            if self.version >= 7 {
+                self.apply_version_information(copy);
+            }
+            let scores = self.penalty_scores(copy);
+            let score = scores.iter().sum();
             if best == 0 || score < best {
                 best = score;
                 best_pattern = pattern;
             }
+            penalties.push(MaskPenalty { pattern, scores, total: score });
         }
+        let best_pattern = self.fixed_mask.unwrap_or(best_pattern);
 
         self.apply_mask_pattern(body, best_pattern);
         self.encode_format_areas(body, best_pattern as u8);
@@ -223,6 +424,16 @@ impl QRConfig {
         if self.version >= 7 {
             self.apply_version_information(body);
         }
+
+        self.mask = best_pattern;
+        let format_bits = self.format_bits(best_pattern as u8);
+        let version_bits = if self.version >= 7 { Some(self.version_bits()) } else { None };
+        if let Some(ref mut trace) = self.trace {
+            trace.mask_penalties = penalties;
+            trace.mask = best_pattern;
+            trace.format_bits = format_bits;
+            trace.version_bits = version_bits;
+        }
     }
     
     pub fn get_ecc_length(&self) -> usize {
@@ -253,7 +464,7 @@ impl QRConfig {
     pub fn apply_version_information(&self, body: &mut Vec<Cell>) {
         let canvas_size = self.size as isize;
         let origin = (canvas_size - 12) as isize;
-        let bit_string = ecc_format_u32(self.version as u32, GEN_POLY_VERSION);
+        let bit_string = self.version_bits();
         let upper_right_indices = get_indices_for_dimensions(origin, 1, canvas_size - 3);
         let lower_left_indices = get_indices_for_dimensions(origin * canvas_size, canvas_size, (-canvas_size * 3) + 1);
         // panic!("VERSION BINARY {:018b}", bit_string);
@@ -286,7 +497,13 @@ impl QRConfig {
         }
     }
 
-    pub fn encode_format_areas(&self, body: &mut Vec<Cell>, pattern: u8) {
+    // the 18 bit version information string, for versions 7 and up
+    pub fn version_bits(&self) -> u32 {
+        ecc_format_u32(self.version as u32, GEN_POLY_VERSION)
+    }
+
+    // the masked 15 bit format information string for a mask pattern
+    pub fn format_bits(&self, pattern: u8) -> u16 {
         let ec_level: u8 = match self.err_correction_level {
             ECLevel::Low => 1,
             ECLevel::Medium => 0,
@@ -295,7 +512,11 @@ impl QRConfig {
         };
 
         let data = (ec_level << 3) | pattern;
-        let format_str = ecc_format_u16(data as u16, GEN_POLY_FORMAT, ECC_FORMAT_MASK);
+        ecc_format_u16(data as u16, GEN_POLY_FORMAT, ECC_FORMAT_MASK)
+    }
+
+    pub fn encode_format_areas(&self, body: &mut Vec<Cell>, pattern: u8) {
+        let format_str = self.format_bits(pattern);
 
         let mut bit_position = 14;
 
@@ -364,17 +585,22 @@ impl QRConfig {
         }
     }
 
-    pub fn eval_penalty_scores(&self, body: &Vec<Cell>) -> usize {
-        let one = self.penalty_score_eval_one(body);
-        let two = self.penalty_score_eval_two(body);
-        let three = self.penalty_score_eval_three(body);
-        let four = self.penalty_score_eval_four(body);
-        let total = one + two + three + four;
+    pub fn eval_penalty_scores(&self, body: &[Cell]) -> usize {
+        self.penalty_scores(body).iter().sum()
+    }
 
-        total
+    // the penalty for each of the four evaluation rules
+    pub fn penalty_scores(&self, body: &[Cell]) -> [usize; 4] {
+        [
+            self.penalty_score_eval_one(body),
+            self.penalty_score_eval_two(body),
+            self.penalty_score_eval_three(body),
+            self.penalty_score_eval_four(body)
+        ]
     }
 
-    pub fn penalty_score_eval_two(&self, body: &Vec<Cell>) -> usize {
+    // 3 points for every 2x2 block of one colour, blocks overlapping
+    pub fn penalty_score_eval_two(&self, body: &[Cell]) -> usize {
         let mut penalty_total = 0;
         let canvas_size = self.size;
 
@@ -388,7 +614,7 @@ impl QRConfig {
             for y in 0..(canvas_size - 1) {
                 let idx = (x * canvas_size) + y;
                 let is_black = body[idx].is_black();
-                let square = adjacent_coords.into_iter()
+                let square = adjacent_coords.iter()
                     .map(|&i| body[i + idx].is_black())
                     .all(|p| p == is_black);
 
@@ -401,160 +627,72 @@ impl QRConfig {
         penalty_total
     }
 
-
-    fn check_column(&self, body: &Vec<Cell>, column: isize) -> usize {
-        let mut subtotal = 0;
-        let pattern_mask: u16 = 0b00001011101;
-        let reverse_mask: u16 = 0b10111010000;
-        let remap_pattern: u16 = 0b11111111111;
-        let mut current_pattern = 0;
-        for row in 0..self.size {
-            let idx = (row * self.size) + column as usize;
-            let cell = &body[idx];
-
-            if cell.is_black() {
-                current_pattern = ((current_pattern << 1) ^ 1) & remap_pattern;
-            } else {
-                current_pattern = (current_pattern << 1) & remap_pattern;
-            }
-
-            if row >= 9 && current_pattern == pattern_mask || current_pattern == reverse_mask {
-                subtotal += 40;
-            }
-        }
-
-        subtotal
+    // the colours along a row, or down a column when `across` is false
+    fn line(&self, body: &[Cell], index: usize, across: bool) -> Vec<bool> {
+        (0..self.size)
+            .map(|i| if across { index * self.size + i } else { i * self.size + index })
+            .map(|idx| body[idx].is_black())
+            .collect()
     }
 
-    fn check_row(&self, body: &Vec<Cell>, row: isize) -> usize {
-        let mut subtotal = 0;
-        let pattern_mask: u16 = 0b00001011101;
-        let reverse_mask: u16 = 0b10111010000;
-        let remap_pattern: u16 = 0b11111111111;
-        let mut current_pattern = 0;
-        for column in 0..self.size {
-            let idx = ((row as usize) * self.size) + column as usize;
-            let cell = &body[idx];
-
-            if cell.is_black() {
-                current_pattern = ((current_pattern << 1) ^ 1) & remap_pattern;
-            } else {
-                current_pattern = (current_pattern << 1) & remap_pattern;
-            }
-
-            if column >= 9 && current_pattern == pattern_mask || current_pattern == reverse_mask {
-                subtotal += 40;
-            }
-        }
-
-        subtotal
-    }
-
-    pub fn penalty_score_eval_three(&self, body: &Vec<Cell>) -> usize {
+    // 40 points for every dark:light:dark:dark:dark:light:dark run in a row
+    // or column with 4 light modules before or after it. The quiet zone
+    // beyond the edge counts as light.
+    pub fn penalty_score_eval_three(&self, body: &[Cell]) -> usize {
+        let finder = [true, false, true, true, true, false, true];
         let mut penalty_total = 0;
 
-        for i in 0..self.size {
-            penalty_total += self.check_column(body, i as isize);
-            penalty_total += self.check_row(body, i as isize);
+        for index in 0..self.size {
+            for &across in [true, false].iter() {
+                let line = self.line(body, index, across);
+                for start in 0..(line.len() - finder.len() + 1) {
+                    let end = start + finder.len();
+                    if line[start..end] != finder {
+                        continue;
+                    }
+                    let light_before = line[start.saturating_sub(4)..start].iter().all(|dark| !dark);
+                    let light_after = line[end..(end + 4).min(line.len())].iter().all(|dark| !dark);
+                    if light_before || light_after {
+                        penalty_total += 40;
+                    }
+                }
+            }
         }
         penalty_total
     }
 
-    pub fn penalty_score_eval_one(&self, body: &Vec<Cell>) -> usize {
-        let canvas_size = self.size;
-        let mut current_row = 0;
-        let mut consecutive_same_color = 1;
-        let mut tracking_black = false;
+    // 3 points for every run of 5 modules of one colour in a row or column,
+    // and 1 more for each module the run goes on past 5
+    pub fn penalty_score_eval_one(&self, body: &[Cell]) -> usize {
         let mut penalty_total = 0;
 
-        for cell in body {
-            if cell.point.0 != current_row {
-                // new row, so reset again
-                if consecutive_same_color >= 5 {
-                    penalty_total += consecutive_same_color - 2;
-                }
-                consecutive_same_color = 1;
-                tracking_black = cell.is_black();
-                current_row = cell.point.0;
-                continue;
-            }
-            let is_black = cell.is_black();
-
-            if (tracking_black && is_black) || (!tracking_black && !is_black) {
-                consecutive_same_color += 1;
-            } else if (tracking_black && !is_black) || (!tracking_black && is_black) {
-                // tally up and reset
-                if consecutive_same_color >= 5 {
-                    penalty_total += consecutive_same_color - 2;
-                }
-
-                consecutive_same_color = 1;
-                tracking_black = is_black;
-            }
-        }
-
-        let cell_count = body.len();
-        let mut idx = 0;
-
-        while idx < cell_count {
-            let cell = &body[idx];
-            let is_black = cell.is_black();
-
-            if (tracking_black && is_black) || (!tracking_black && !is_black) {
-                consecutive_same_color += 1;
-            } else if (tracking_black && !is_black) || (!tracking_black && is_black) {
-                // tally up and reset
-                if consecutive_same_color >= 5 {
-                    penalty_total += consecutive_same_color - 2;
+        for index in 0..self.size {
+            for &across in [true, false].iter() {
+                let line = self.line(body, index, across);
+                let mut run = 0;
+                for (i, &is_black) in line.iter().enumerate() {
+                    run += 1;
+                    // tally up at the end of each run
+                    if i + 1 == line.len() || line[i + 1] != is_black {
+                        if run >= 5 {
+                            penalty_total += run - 2;
+                        }
+                        run = 0;
+                    }
                 }
-
-                consecutive_same_color = 1;
-                tracking_black = is_black;
-            }
-
-            if cell.point.1 == (canvas_size - 1) && cell.point.0 == (canvas_size - 1) {
-                break;
-            } else if cell.point.0 == canvas_size - 1 {
-                idx = cell.point.1 + 1;
-                consecutive_same_color = 1;
-            } else {
-                idx += canvas_size;
             }
         }
 
         penalty_total
     }
 
-    pub fn penalty_score_eval_four(&self, body: &Vec<Cell>) -> usize {
-        // total modules
-        let total_modules = body.len() as f64;
-        let black_modules: f64 = body.iter()
-            .fold(0.0, |acc, ref c| {
-                if c.is_black() {
-                    acc + 1.0
-                } else {
-                    acc
-                }
-            });
-
-        let black_percentage = ((black_modules / total_modules) * 100.0).round() as usize;
-        let remainder = black_percentage % 5;
-
-        let prev_mul = black_percentage - remainder;
-        let next_mul = prev_mul + 5;
-
-        let prev_abs = (50 - prev_mul as isize).abs();
-        let next_abs = (50 - next_mul as isize).abs();
+    // 10 points for every full 5% the share of dark modules is away from half
+    pub fn penalty_score_eval_four(&self, body: &[Cell]) -> usize {
+        let total_modules = body.len() as isize;
+        let black_modules = body.iter().filter(|cell| cell.is_black()).count() as isize;
 
-        let prev_div = prev_abs / 5;
-        let next_div = next_abs / 5;
-
-
-        if prev_div < next_div {
-            (prev_div * 10) as usize
-        } else {
-            (next_div * 10) as usize
-        }
+        let steps = (black_modules * 20 - total_modules * 10).abs() / total_modules;
+        steps as usize * 10
     }
 
     pub fn verify_version(&mut self) {
@@ -572,19 +710,6 @@ impl QRConfig {
         // }
     }
 
-    pub fn debug_data(&self) {
-        let ref data = self.data;
-        let ref codewords = self.codewords;
-        println!("data {}, codewords {}", data.len(), codewords.len());
-        println!("cw: {:?}", self.codeword_properties);
-        // for (idx, byte) in codewords.iter().enumerate() {
-        //     if let Some(data_byte) = data.get(idx + 2) {
-        //         println!("{:b} original", 112 << 4);
-        //     }
-        //     println!("{:b} byte #: {} ", idx, byte);
-        // }
-    }
-
     pub fn encode_error_correction_codewords(&mut self) {
         let ecc_len = self.codeword_properties.ecc_codeword_count;
         let ecc_per_block = ecc_len / self.codeword_properties.block_count;
@@ -615,7 +740,17 @@ impl QRConfig {
                 group_two.codewords_per_block
             };
 
+            if let Some(ref mut trace) = self.trace {
+                trace.data_codewords = data_codewords.clone();
+                trace.blocks = blocks.iter()
+                    .map(|block| BlockTrace { data: block.data().to_vec(), ecc: block.ecc().to_vec() })
+                    .collect();
+            }
+
             let mut interleaved_data = interleave_blocks(&blocks[..], codeword_max, ecc_per_block);
+            if let Some(ref mut trace) = self.trace {
+                trace.interleaved = interleaved_data.clone();
+            }
             data_section.append(&mut interleaved_data);
         }
 
@@ -624,73 +759,33 @@ impl QRConfig {
 
 
     pub fn translate_data(&mut self) {
-        let data_cw_length = self.codeword_properties.get_data_codeword_length();
-        let data_length = self.data.len() as u16;
-        let encoding = self.encoding;
-        let copied_data = self.data.clone();
-        {
-            let codewords = &mut self.codewords;
-            let mut first_byte = encoding << 4;
-            let mut second_byte: u8 = data_length as u8;
-
-            if self.version > 9 {
-                second_byte = (data_length >> 8) as u8;
-                codewords.push(first_byte | (second_byte >> 4));
-                first_byte = second_byte << 4;
-                second_byte = data_length as u8;
-            } else {
-                second_byte = data_length as u8;
+        let capacity = self.codeword_properties.get_data_codeword_length() * 8;
+        let mut buffer = BitBuffer::new();
+        let mut segment_traces = vec![];
+
+        for segment in self.message_segments() {
+            let mut bits = BitBuffer::new();
+            segment.write(&mut bits, self.version);
+
+            if self.trace.is_some() {
+                segment_traces.push(SegmentTrace {
+                    mode: segment.name(),
+                    char_count: segment.char_count(),
+                    bits: bits.to_bit_string()
+                });
             }
 
-            /*
-                what am I doing...
-
-                1. if there's a 16 bit integer, it needs to be broken up|
-                2. the first half of the 16 bit integer is used first
-                3. cast it to an 8 bit integer
-                4. bitwise operation normally with the mode byte
-                
-            */
-
-            let mut index = 0;
-
-            loop {
-                codewords.push(first_byte | (second_byte >> 4));
-                first_byte = second_byte << 4;
-
-                if let Some(byte) = copied_data.get(index) {
-                    second_byte = *byte;
-                    index += 1;
-                } else {
-                    codewords.push(second_byte << 4);
-                    break;
-                }
-            }
+            buffer.append_buffer(&bits);
         }
 
-        // pad the end of the message codewords, alternating between 17 and 236, until it fills the allotted amount for the version
-
-
-        let mut swap = false;
-        let mut n = 0;
-        while self.codewords.len() < data_cw_length {
-            n += 1;
-            if swap {
-                self.codewords.push(17u8);
-            } else {
-                self.codewords.push(236u8);
-            }
+        let padding = buffer.terminate(capacity);
+        self.codewords = buffer.into_bytes();
 
-            swap = !swap;
-        }
-
-        if self.debug_mode {
-            println!("THIS IS HOW MANY PADDED BYTES ARE ADDED BEFORE INTERLEAVING {}", n);
-
-            println!("after translate {}", self.codewords.len());
-            for (idx, cw) in self.codewords.iter().enumerate() {
-                println!("Codeword {}:  {:08b}", idx, cw);
-            }
+        if let Some(ref mut trace) = self.trace {
+            trace.segments = segment_traces;
+            trace.terminator_bits = padding.terminator_bits;
+            trace.bit_padding = padding.bit_padding;
+            trace.pad_codewords = padding.pad_codewords;
         }
     }
 
@@ -713,23 +808,7 @@ impl QRConfig {
     }
 
     pub fn get_content_length(&self) -> usize {
-        let modifier = match self.version {
-            1...10 => 0,
-            11...27 => 2,
-            _ => 4
-        };
-        match self.encoding {
-            1 => 10 + modifier,
-            2 => 9 + modifier,
-            8 => 12 + modifier,
-            _ => {
-                if self.version < 10 {
-                    8
-                } else {
-                    16
-                }
-            }
-        }
+        get_content_length(self.encoding, self.version)
     }
 
     pub fn apply_version_information_areas(&self, body: &mut Vec<Cell>) {
@@ -935,32 +1014,7 @@ impl QRConfig {
     }
 
     pub fn get_alignment_points(&self, body: &Vec<Cell>) -> Vec<PlotPoint> {
-        let mut pts: Vec<usize> = vec![6];
-        let mut n = 6;
-        // let last_column = self.size - 7;
-        let version_bracket = match self.version {
-            1 => 0,
-            2...6 => 1,
-            7...13 => 2,
-            14...21 => 3,
-            22...28 => 4,
-            29...36 => 5,
-            37...41 => 6,
-            _ => 0
-        };
-
-
-
-        let modifier = if version_bracket == 1 {
-            self.size - 13
-        } else {
-            (self.size - 12) / version_bracket
-        };
-
-        for _ in 0..version_bracket {
-            n += modifier;
-            pts.push(n);
-        }
+        let pts: Vec<usize> = alignment_pattern_centers(self.version).to_vec();
 
         let pts: Vec<PlotPoint> = self.get_point_combinations(pts)
             .into_iter()
@@ -0,0 +1,179 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use qr_encoder::cell::{CellType, Color};
+use qr_encoder::config::CellTrack;
+use qr_encoder::qr::QR;
+use qr_encoder::style::hex;
+
+// what a module is used for, as far as placement is concerned
+#[derive(Debug, Clone)]
+pub enum ModuleRole {
+    Function(CellType),
+    Data { codeword: usize, bit: usize },
+    Ecc { codeword: usize, bit: usize },
+    Remainder
+}
+
+impl ModuleRole {
+    pub fn codeword(&self) -> Option<usize> {
+        match *self {
+            ModuleRole::Data { codeword, .. } | ModuleRole::Ecc { codeword, .. } => Some(codeword),
+            _ => None
+        }
+    }
+
+    // the first bit of a codeword to be placed, where its index gets labelled
+    pub fn is_first_bit(&self) -> bool {
+        match *self {
+            ModuleRole::Data { bit, .. } | ModuleRole::Ecc { bit, .. } => bit == 7,
+            _ => false
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match *self {
+            ModuleRole::Function(ref cell_type) => match *cell_type {
+                CellType::Finder => Color { r: 220, g: 50, b: 47 },
+                CellType::Alignment => Color { r: 211, g: 54, b: 130 },
+                CellType::Separator => Color { r: 230, g: 220, b: 190 },
+                CellType::Timing => Color { r: 108, g: 113, b: 196 },
+                CellType::DarkModule => Color { r: 181, g: 137, b: 0 },
+                CellType::VersionInformation => Color { r: 200, g: 200, b: 123 },
+                CellType::Format => Color { r: 10, g: 140, b: 230 },
+                _ => Color { r: 255, g: 255, b: 255 }
+            },
+            ModuleRole::Data { .. } => Color { r: 133, g: 153, b: 0 },
+            ModuleRole::Ecc { .. } => Color { r: 203, g: 75, b: 22 },
+            ModuleRole::Remainder => Color { r: 147, g: 161, b: 161 }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    pub role: ModuleRole,
+    pub dark: bool
+}
+
+impl Annotation {
+    // the role colour, darkened for dark modules and washed out for light ones
+    pub fn color(&self) -> Color {
+        let base = self.role.color();
+        if self.dark {
+            Color { r: base.r / 2, g: base.g / 2, b: base.b / 2 }
+        } else {
+            Color {
+                r: base.r + (255 - base.r) * 3 / 5,
+                g: base.g + (255 - base.g) * 3 / 5,
+                b: base.b + (255 - base.b) * 3 / 5
+            }
+        }
+    }
+}
+
+// works out the role of every module in the symbol from the placement order
+// recorded while encoding. `data_codewords` is the number of codewords that
+// precede the error correction codewords in the interleaved stream.
+pub fn annotate(qr: &QR, tracker: &CellTrack, data_codewords: usize) -> Vec<Annotation> {
+    // placement follows the symbol as encoded, before any inversion or mirroring
+    let qr = &qr.restore();
+    let mut annotations: Vec<Annotation> = qr.body.iter()
+        .map(|cell| {
+            let role = match cell.module_type {
+                CellType::Message => ModuleRole::Remainder,
+                ref cell_type => ModuleRole::Function(cell_type.clone())
+            };
+            Annotation { role, dark: cell.is_black() }
+        })
+        .collect();
+
+    let CellTrack(ref bit_order, ref cw_order, ref point_order) = *tracker;
+    for ((bit, codeword), idx) in bit_order.iter().zip(cw_order.iter()).zip(point_order.iter()) {
+        // the placement path also visits function modules, which get skipped over
+        if let ModuleRole::Function(_) = annotations[*idx].role {
+            continue;
+        }
+
+        annotations[*idx].role = if *codeword < data_codewords {
+            ModuleRole::Data { codeword: *codeword, bit: *bit }
+        } else {
+            ModuleRole::Ecc { codeword: *codeword, bit: *bit }
+        };
+    }
+
+    annotations
+}
+
+// sides of a module that border a module outside its codeword:
+// top, right, bottom, left
+pub fn codeword_edges(annotations: &[Annotation], width: usize, row: usize, col: usize) -> [bool; 4] {
+    let codeword = annotations[row * width + col].role.codeword();
+    if codeword.is_none() {
+        return [false; 4];
+    }
+
+    let differs = |r: isize, c: isize| {
+        if r < 0 || c < 0 || r as usize >= width || c as usize >= width {
+            return true;
+        }
+        annotations[r as usize * width + c as usize].role.codeword() != codeword
+    };
+
+    let (r, c) = (row as isize, col as isize);
+    [differs(r - 1, c), differs(r, c + 1), differs(r + 1, c), differs(r, c - 1)]
+}
+
+// an SVG where every module is shaded by its role, each codeword's modules
+// are outlined and labelled with the codeword's index
+pub fn to_debug_svg(qr: &QR, annotations: &[Annotation], module_size: usize) -> String {
+    let width = qr.width();
+    let quiet_zone = 4;
+    let pixels = (width + quiet_zone * 2) * module_size;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">\
+<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n",
+        pixels
+    );
+    let mut outlines = String::new();
+    let mut labels = String::new();
+
+    for row in 0..width {
+        for col in 0..width {
+            let annotation = &annotations[row * width + col];
+            let x = (col + quiet_zone) * module_size;
+            let y = (row + quiet_zone) * module_size;
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                x, y, module_size, module_size, hex(annotation.color())
+            ));
+
+            let edges = codeword_edges(annotations, width, row, col);
+            let sides = [
+                (x, y, x + module_size, y),
+                (x + module_size, y, x + module_size, y + module_size),
+                (x, y + module_size, x + module_size, y + module_size),
+                (x, y, x, y + module_size)
+            ];
+            for (edge, side) in edges.iter().zip(sides.iter()) {
+                if *edge {
+                    outlines.push_str(&format!("M{},{}L{},{}", side.0, side.1, side.2, side.3));
+                }
+            }
+
+            if annotation.role.is_first_bit() {
+                labels.push_str(&format!(
+                    "<text x=\"{}\" y=\"{}\">{}</text>\n",
+                    x + 1, y + module_size - 2, annotation.role.codeword().unwrap()
+                ));
+            }
+        }
+    }
+
+    svg.push_str(&format!("<path d=\"{}\" stroke=\"#000000\" stroke-width=\"1\" fill=\"none\"/>\n", outlines));
+    svg.push_str(&format!(
+        "<g font-family=\"monospace\" font-size=\"{}\" fill=\"#000000\">\n{}</g>\n</svg>\n",
+        module_size / 2, labels
+    ));
+    svg
+}
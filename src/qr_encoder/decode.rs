@@ -0,0 +1,316 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use qr_encoder::capacity::EC_LEVELS;
+use qr_encoder::cell::{Cell, CellType};
+use qr_encoder::config::{QRConfig, ECLevel, zig_zag_points};
+use qr_encoder::error::QRError;
+use qr_encoder::qr::QR;
+use qr_encoder::segment::{Segment, read_segments};
+use qr_encoder::util::codeword_info;
+use qr_encoder::version::Version;
+
+// format information has a Hamming distance of 7 between valid patterns, so
+// across its two copies anything closer than this is taken as a match
+const MAX_FORMAT_DISTANCE: usize = 7;
+
+// what the format modules look like for a level and mask pattern
+struct FormatCandidate {
+    err_correction_level: ECLevel,
+    mask: usize,
+    modules: Vec<(usize, bool)>
+}
+
+// reads module matrices of one version back into their message. Built once
+// per version, since working out the layout is the costly part.
+pub struct MatrixDecoder {
+    version: usize,
+    width: usize,
+    // the data and error correction modules in placement order
+    data_path: Vec<usize>,
+    layout: Vec<Cell>,
+    formats: Vec<FormatCandidate>
+}
+
+impl MatrixDecoder {
+    pub fn new(version: Version) -> MatrixDecoder {
+        let config = QRConfig::new(version, vec![], ECLevel::Low);
+        let mut qr = QR::new(config.create_body(), ECLevel::Low);
+        qr.setup(&config);
+
+        let data_path = zig_zag_points(config.size).into_iter()
+            .filter(|idx| matches!(qr.body[*idx].module_type, CellType::None))
+            .collect();
+
+        let mut formats = vec![];
+        for level in EC_LEVELS.iter() {
+            let config = QRConfig::new(version, vec![], *level);
+            for mask in 0..8 {
+                let mut body = qr.body.clone();
+                config.encode_format_areas(&mut body, mask as u8);
+                let modules = body.iter().enumerate()
+                    .filter(|&(_, cell)| matches!(cell.module_type, CellType::Format))
+                    .map(|(idx, cell)| (idx, cell.is_black()))
+                    .collect();
+                formats.push(FormatCandidate { err_correction_level: *level, mask, modules });
+            }
+        }
+
+        MatrixDecoder { version: config.version, width: config.size, data_path, layout: qr.body, formats }
+    }
+
+    // the level and mask whose format information is nearest to what's in the matrix
+    fn read_format(&self, modules: &[u8], erased: &[bool]) -> Result<(ECLevel, usize), QRError> {
+        let distance = |candidate: &FormatCandidate| candidate.modules.iter()
+            .filter(|&&(idx, dark)| !erased[idx] && (modules[idx] == 1) != dark)
+            .count();
+
+        let nearest = self.formats.iter().min_by_key(|candidate| distance(candidate)).unwrap();
+        if distance(nearest) >= MAX_FORMAT_DISTANCE {
+            return Err(QRError::Undecodable(String::from("the format information is unreadable")));
+        }
+        Ok((nearest.err_correction_level, nearest.mask))
+    }
+
+    // decodes a matrix of `width * width` modules in row-major order, 1 for
+    // dark, into its segments. `erased` marks modules known to be unreadable.
+    pub fn decode_segments(&self, modules: &[u8], erased: &[bool]) -> Result<Vec<Segment>, QRError> {
+        if modules.len() != self.width * self.width || erased.len() != modules.len() {
+            return Err(QRError::Undecodable(format!("expected {} modules for version {}", self.width * self.width, self.version)));
+        }
+
+        let (level, mask) = self.read_format(modules, erased)?;
+        let config = QRConfig::new(Version::new(self.version)?, vec![], level);
+        let pattern = config.get_mask_pattern(mask);
+        let properties = codeword_info(self.version, &level);
+
+        // unmask and read the codewords, noting which ones lost a module
+        let mut codewords = vec![0u8; properties.capacity];
+        let mut erased_codewords = vec![false; properties.capacity];
+        for (bit, idx) in self.data_path.iter().take(properties.capacity * 8).enumerate() {
+            let point = self.layout[*idx].point;
+            let dark = (modules[*idx] == 1) != pattern(point.0, point.1);
+            if dark {
+                codewords[bit / 8] |= 0x80 >> (bit % 8);
+            }
+            if erased[*idx] {
+                erased_codewords[bit / 8] = true;
+            }
+        }
+
+        // undo the interleaving into blocks of data then error correction codewords
+        let (group_one, group_two) = properties.get_data_cw_total_for_groups();
+        let block_count = properties.block_count;
+        let ecc_per_block = properties.ecc_codeword_count / block_count;
+        let mut sizes = vec![group_one.codewords_per_block; group_one.blocks];
+        sizes.extend(vec![group_two.codewords_per_block; group_two.blocks]);
+        let longest = sizes.iter().cloned().max().unwrap_or(0);
+
+        let mut blocks: Vec<Vec<u8>> = sizes.iter().map(|size| Vec::with_capacity(size + ecc_per_block)).collect();
+        let mut block_erasures: Vec<Vec<usize>> = vec![vec![]; block_count];
+        let mut next = 0;
+        let mut place = |block: usize, blocks: &mut Vec<Vec<u8>>| {
+            if erased_codewords[next] {
+                block_erasures[block].push(blocks[block].len());
+            }
+            blocks[block].push(codewords[next]);
+            next += 1;
+        };
+        for i in 0..longest {
+            for (block, size) in sizes.iter().enumerate() {
+                if i < *size {
+                    place(block, &mut blocks);
+                }
+            }
+        }
+        for _ in 0..ecc_per_block {
+            for block in 0..block_count {
+                place(block, &mut blocks);
+            }
+        }
+
+        let field = Galois::new();
+        let mut data: Vec<u8> = vec![];
+        for (block, erasures) in blocks.iter_mut().zip(block_erasures.iter()) {
+            if erasures.len() > ecc_per_block {
+                return Err(QRError::Undecodable(String::from("too many modules are erased")));
+            }
+            field.correct(block, erasures, ecc_per_block)?;
+            data.extend_from_slice(&block[..block.len() - ecc_per_block]);
+        }
+
+        read_segments(&data, self.version)
+    }
+
+    // decodes a matrix into its message, the data of every segment joined together
+    pub fn decode(&self, modules: &[u8], erased: &[bool]) -> Result<Vec<u8>, QRError> {
+        let mut message = vec![];
+        for segment in self.decode_segments(modules, erased)? {
+            match segment {
+                Segment::Numeric(data) | Segment::AlphaNumeric(data) | Segment::Byte(data) | Segment::Kanji(data) => {
+                    message.extend(data);
+                },
+                Segment::Eci(_) => {}
+            }
+        }
+        Ok(message)
+    }
+}
+
+// arithmetic in GF(256) with the polynomial QR codes use, x^8 + x^4 + x^3 + x^2 + 1.
+// Polynomials are kept lowest power first.
+struct Galois {
+    exp: [u8; 512],
+    log: [u8; 256]
+}
+
+fn too_damaged() -> QRError {
+    QRError::Undecodable(String::from("too many codewords are damaged"))
+}
+
+impl Galois {
+    fn new() -> Galois {
+        let mut exp = [0; 512];
+        let mut log = [0; 256];
+        let mut x: usize = 1;
+        for (i, e) in exp.iter_mut().take(255).enumerate() {
+            *e = x as u8;
+            log[x] = i as u8;
+            x <<= 1;
+            if x & 0x100 != 0 {
+                x ^= 0x11D;
+            }
+        }
+        // doubled up so products never need reducing mod 255
+        let (low, high) = exp.split_at_mut(255);
+        high[..255].copy_from_slice(low);
+        Galois { exp, log }
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+    }
+
+    // `a` must not be zero
+    fn inverse(&self, a: u8) -> u8 {
+        self.exp[255 - self.log[a as usize] as usize]
+    }
+
+    // the generator raised to `power`
+    fn pow(&self, power: usize) -> u8 {
+        self.exp[power % 255]
+    }
+
+    fn eval(&self, poly: &[u8], x: u8) -> u8 {
+        poly.iter().rev().fold(0, |acc, c| self.mul(acc, x) ^ c)
+    }
+
+    fn mul_poly(&self, a: &[u8], b: &[u8]) -> Vec<u8> {
+        let mut product = vec![0; a.len() + b.len() - 1];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                product[i + j] ^= self.mul(*x, *y);
+            }
+        }
+        product
+    }
+
+    fn syndromes(&self, block: &[u8], ecc: usize) -> Vec<u8> {
+        // the first codeword is the highest power
+        (0..ecc).map(|j| block.iter().fold(0, |acc, c| self.mul(acc, self.pow(j)) ^ c)).collect()
+    }
+
+    // corrects a block of data then `ecc` error correction codewords in
+    // place, with the codewords at `erasures` known to be unreliable. Errors
+    // cost two error correction codewords and erasures one; past that the
+    // block is given up on, never guessed at.
+    fn correct(&self, block: &mut [u8], erasures: &[usize], ecc: usize) -> Result<(), QRError> {
+        let n = block.len();
+        let syndromes = self.syndromes(block, ecc);
+        if syndromes.iter().all(|s| *s == 0) {
+            return Ok(());
+        }
+
+        // Berlekamp-Massey, starting from the erasure locator so it only has
+        // to find the errors
+        let mut locator = vec![1];
+        for &pos in erasures.iter() {
+            locator = self.mul_poly(&locator, &[1, self.pow(n - 1 - pos)]);
+        }
+        let mut previous = locator.clone();
+        let mut length = erasures.len();
+        for r in erasures.len()..ecc {
+            let delta = (0..locator.len().min(r + 1)).fold(0, |acc, i| acc ^ self.mul(locator[i], syndromes[r - i]));
+            previous.insert(0, 0);
+            if delta != 0 {
+                let mut next = locator.clone();
+                next.resize(next.len().max(previous.len()), 0);
+                for (i, c) in previous.iter().enumerate() {
+                    next[i] ^= self.mul(delta, *c);
+                }
+                if 2 * length <= r + erasures.len() {
+                    let inverse = self.inverse(delta);
+                    previous = locator.iter().map(|c| self.mul(*c, inverse)).collect();
+                    length = r + 1 + erasures.len() - length;
+                }
+                locator = next;
+            }
+        }
+        while locator.len() > 1 && locator[locator.len() - 1] == 0 {
+            locator.pop();
+        }
+        let degree = locator.len() - 1;
+        if degree < erasures.len() || 2 * (degree - erasures.len()) + erasures.len() > ecc {
+            return Err(too_damaged());
+        }
+
+        // the locator's roots give the positions, and there must be one for
+        // each degree or the damage is past what can be located
+        let positions: Vec<usize> = (0..n)
+            .filter(|&pos| self.eval(&locator, self.inverse(self.pow(n - 1 - pos))) == 0)
+            .collect();
+        if positions.len() != degree {
+            return Err(too_damaged());
+        }
+
+        // Forney's formula for the size of each error
+        let mut evaluator = self.mul_poly(&syndromes, &locator);
+        evaluator.truncate(ecc);
+        let derivative: Vec<u8> = locator.iter().enumerate().skip(1)
+            .map(|(i, c)| if i % 2 == 1 { *c } else { 0 })
+            .collect();
+        for &pos in positions.iter() {
+            let x = self.pow(n - 1 - pos);
+            let x_inverse = self.inverse(x);
+            let denominator = self.eval(&derivative, x_inverse);
+            if denominator == 0 {
+                return Err(too_damaged());
+            }
+            let magnitude = self.mul(self.mul(x, self.eval(&evaluator, x_inverse)), self.inverse(denominator));
+            block[pos] ^= magnitude;
+        }
+
+        if self.syndromes(block, ecc).iter().any(|s| *s != 0) {
+            return Err(too_damaged());
+        }
+        Ok(())
+    }
+}
+
+// the version of a symbol `width` modules across
+pub fn version_for_width(width: usize) -> Result<Version, QRError> {
+    if width < 21 || !(width - 17).is_multiple_of(4) {
+        return Err(QRError::Undecodable(format!("{} modules is not the width of any version", width)));
+    }
+    Version::new((width - 17) / 4)
+}
+
+// decodes a symbol, undoing any inversion or mirroring first
+pub fn decode(qr: &QR) -> Result<Vec<u8>, QRError> {
+    let qr = qr.restore();
+    let decoder = MatrixDecoder::new(version_for_width(qr.width())?);
+    decoder.decode(&qr.modules(), &vec![false; qr.body.len()])
+}
@@ -0,0 +1,280 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use qr_encoder::config::{QRConfig, ECLevel};
+use qr_encoder::error::QRError;
+use qr_encoder::payload::{Payload, encode_mixed};
+use qr_encoder::version::Version;
+
+// tags of the EMVCo merchant-presented mode data objects
+pub const PAYLOAD_FORMAT: u8 = 0;
+pub const POINT_OF_INITIATION: u8 = 1;
+pub const MERCHANT_CATEGORY: u8 = 52;
+pub const CURRENCY: u8 = 53;
+pub const AMOUNT: u8 = 54;
+pub const COUNTRY: u8 = 58;
+pub const MERCHANT_NAME: u8 = 59;
+pub const MERCHANT_CITY: u8 = 60;
+pub const POSTAL_CODE: u8 = 61;
+pub const ADDITIONAL_DATA: u8 = 62;
+pub const CRC: u8 = 63;
+
+fn invalid(reason: String) -> QRError {
+    QRError::InvalidPayload(reason)
+}
+
+// CRC-16/CCITT-FALSE: polynomial 0x1021, starting from 0xFFFF, as tag 63
+// carries over the whole payload up to and including its own tag and length
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+// tags whose values are themselves lists of data objects: merchant account
+// information, additional data, the merchant language template and the
+// unreserved templates
+fn is_template(tag: u8) -> bool {
+    (26..=51).contains(&tag) || tag == ADDITIONAL_DATA || tag == 64 || tag >= 80
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TlvValue {
+    Primitive(String),
+    Template(Vec<Tlv>)
+}
+
+// a data object: a two digit tag, a two digit length and the value
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tlv {
+    pub tag: u8,
+    pub value: TlvValue
+}
+
+impl Tlv {
+    pub fn primitive(tag: u8, value: &str) -> Tlv {
+        Tlv { tag, value: TlvValue::Primitive(String::from(value)) }
+    }
+
+    pub fn template(tag: u8, fields: Vec<Tlv>) -> Tlv {
+        Tlv { tag, value: TlvValue::Template(fields) }
+    }
+
+    // a merchant account information template: the payment network's
+    // globally unique identifier in tag 00, followed by its own fields
+    pub fn merchant_account(tag: u8, guid: &str, fields: Vec<Tlv>) -> Tlv {
+        let mut all = vec![Tlv::primitive(0, guid)];
+        all.extend(fields);
+        Tlv::template(tag, all)
+    }
+
+    // the value as it is written out
+    pub fn value_string(&self) -> Result<String, QRError> {
+        match self.value {
+            TlvValue::Primitive(ref value) => Ok(value.clone()),
+            TlvValue::Template(ref fields) => {
+                let mut value = String::new();
+                for field in fields.iter() {
+                    field.write(&mut value)?;
+                }
+                Ok(value)
+            }
+        }
+    }
+
+    pub fn write(&self, out: &mut String) -> Result<(), QRError> {
+        if self.tag > 99 {
+            return Err(invalid(format!("tag {} is not between 00 and 99", self.tag)));
+        }
+        let value = self.value_string()?;
+        let length = value.chars().count();
+        if length == 0 || length > 99 {
+            return Err(invalid(format!("tag {:02} is {} characters long but must be 1 to 99", self.tag, length)));
+        }
+        out.push_str(&format!("{:02}{:02}{}", self.tag, length, value));
+        Ok(())
+    }
+
+    // the first field with `tag` in a template
+    pub fn field(&self, tag: u8) -> Option<&Tlv> {
+        match self.value {
+            TlvValue::Template(ref fields) => find(fields, tag),
+            TlvValue::Primitive(_) => None
+        }
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        match self.value {
+            TlvValue::Primitive(ref value) => Some(value),
+            TlvValue::Template(_) => None
+        }
+    }
+}
+
+// the first data object with `tag`
+pub fn find(fields: &[Tlv], tag: u8) -> Option<&Tlv> {
+    fields.iter().find(|field| field.tag == tag)
+}
+
+// reads a list of data objects, reading into templates as it goes
+pub fn parse_tlv(data: &str) -> Result<Vec<Tlv>, QRError> {
+    let chars: Vec<char> = data.chars().collect();
+    let number = |from: usize| -> Result<usize, QRError> {
+        chars.get(from..from + 2)
+            .filter(|digits| digits.iter().all(|digit| digit.is_ascii_digit()))
+            .map(|digits| digits.iter().fold(0, |number, digit| number * 10 + (*digit as usize - '0' as usize)))
+            .ok_or_else(|| invalid(format!("expected a two digit number at character {}", from)))
+    };
+
+    let mut fields = vec![];
+    let mut position = 0;
+    while position < chars.len() {
+        let tag = number(position)? as u8;
+        let length = number(position + 2)?;
+        let start = position + 4;
+        let value: String = match chars.get(start..start + length) {
+            Some(value) => value.iter().collect(),
+            None => return Err(invalid(format!("tag {:02} runs past the end of the data", tag)))
+        };
+        fields.push(if is_template(tag) {
+            Tlv::template(tag, parse_tlv(&value)?)
+        } else {
+            Tlv::primitive(tag, &value)
+        });
+        position = start + length;
+    }
+    Ok(fields)
+}
+
+// reads a merchant-presented payload, checking it starts with the payload
+// format indicator and ends with a CRC that matches
+pub fn parse_emv(payload: &str) -> Result<Vec<Tlv>, QRError> {
+    let crc_start = match payload.len().checked_sub(8) {
+        Some(start) if payload.is_char_boundary(start) && payload[start..].starts_with("6304") => start,
+        _ => return Err(invalid(String::from("the payload doesn't end with a CRC in tag 63")))
+    };
+    let expected = format!("{:04X}", crc16(&payload.as_bytes()[..crc_start + 4]));
+    if !payload[crc_start + 4..].eq_ignore_ascii_case(&expected) {
+        return Err(invalid(format!("the CRC is {} but the payload's is {}", &payload[crc_start + 4..], expected)));
+    }
+
+    // the CRC text must be tag 63 itself, not the tail of another field's value
+    let fields = parse_tlv(payload)?;
+    if fields.last().map(|field| field.tag) != Some(CRC) {
+        return Err(invalid(String::from("the payload doesn't end with a CRC in tag 63")));
+    }
+    match fields.first() {
+        Some(field) if field.tag == PAYLOAD_FORMAT && field.text() == Some("01") => Ok(fields),
+        _ => Err(invalid(String::from("the payload doesn't start with payload format indicator 01")))
+    }
+}
+
+// a merchant-presented mode payload, as printed at a point of sale
+#[derive(Clone, Debug, PartialEq)]
+pub struct Emv {
+    // a code reused for every payment, or one made for a single payment
+    pub dynamic: bool,
+    // merchant account information, tags 02 to 51
+    pub accounts: Vec<Tlv>,
+    // four digit ISO 18245 merchant category code
+    pub category: String,
+    // three digit ISO 4217 numeric currency code
+    pub currency: String,
+    pub amount: Option<String>,
+    // two letter ISO 3166 country code
+    pub country: String,
+    pub merchant_name: String,
+    pub merchant_city: String,
+    pub postal_code: String,
+    // fields of the additional data template, such as the bill number in 01
+    pub additional: Vec<Tlv>,
+    // any other data objects, tags 64 to 99, written before the CRC
+    pub extra: Vec<Tlv>
+}
+
+fn check_digits(field: &str, value: &str, length: usize) -> Result<(), QRError> {
+    if value.len() != length || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid(format!("the {} {:?} is not {} digits", field, value, length)));
+    }
+    Ok(())
+}
+
+fn check_length(field: &str, value: &str, max: usize) -> Result<(), QRError> {
+    let length = value.chars().count();
+    if length == 0 || length > max {
+        return Err(invalid(format!("the {} must be 1 to {} characters", field, max)));
+    }
+    Ok(())
+}
+
+impl Payload for Emv {
+    fn payload(&self) -> Result<String, QRError> {
+        if self.accounts.is_empty() {
+            return Err(invalid(String::from("at least one merchant account is required")));
+        }
+        if let Some(account) = self.accounts.iter().find(|account| !(2..=51).contains(&account.tag)) {
+            return Err(invalid(format!("tag {:02} is not a merchant account tag", account.tag)));
+        }
+        // 00 to 63 are written from the fields above, so a second copy would
+        // be misread, and a 63 would be taken for the CRC
+        if let Some(field) = self.extra.iter().find(|field| field.tag <= CRC) {
+            return Err(invalid(format!("tag {:02} is written by the payload itself and can't be an extra field", field.tag)));
+        }
+        check_digits("merchant category code", &self.category, 4)?;
+        check_digits("currency code", &self.currency, 3)?;
+        if let Some(ref amount) = self.amount {
+            let valid = amount.len() <= 13 && amount.bytes().any(|b| b.is_ascii_digit())
+                && amount.bytes().all(|b| b.is_ascii_digit() || b == b'.')
+                && amount.matches('.').count() <= 1;
+            if !valid {
+                return Err(invalid(format!("{:?} is not an amount of at most 13 characters", amount)));
+            }
+        }
+        if self.country.len() != 2 || !self.country.bytes().all(|b| b.is_ascii_uppercase()) {
+            return Err(invalid(format!("{:?} is not a two letter country code", self.country)));
+        }
+        check_length("merchant name", &self.merchant_name, 25)?;
+        check_length("merchant city", &self.merchant_city, 15)?;
+
+        let mut fields = vec![
+            Tlv::primitive(PAYLOAD_FORMAT, "01"),
+            Tlv::primitive(POINT_OF_INITIATION, if self.dynamic { "12" } else { "11" })
+        ];
+        fields.extend(self.accounts.iter().cloned());
+        fields.push(Tlv::primitive(MERCHANT_CATEGORY, &self.category));
+        fields.push(Tlv::primitive(CURRENCY, &self.currency));
+        if let Some(ref amount) = self.amount {
+            fields.push(Tlv::primitive(AMOUNT, amount));
+        }
+        fields.push(Tlv::primitive(COUNTRY, &self.country));
+        fields.push(Tlv::primitive(MERCHANT_NAME, &self.merchant_name));
+        fields.push(Tlv::primitive(MERCHANT_CITY, &self.merchant_city));
+        if !self.postal_code.is_empty() {
+            fields.push(Tlv::primitive(POSTAL_CODE, &self.postal_code));
+        }
+        if !self.additional.is_empty() {
+            fields.push(Tlv::template(ADDITIONAL_DATA, self.additional.clone()));
+        }
+        fields.extend(self.extra.iter().cloned());
+
+        let mut payload = String::new();
+        for field in fields.iter() {
+            field.write(&mut payload)?;
+        }
+        payload.push_str("6304");
+        let crc = crc16(payload.as_bytes());
+        payload.push_str(&format!("{:04X}", crc));
+        Ok(payload)
+    }
+
+    // merchant names and cities are usually mixed case, so the upper case
+    // tags, lengths and codes go in alphanumeric segments and the rest in bytes
+    fn config(&self, version: Option<Version>, err_correction_level: ECLevel) -> Result<QRConfig, QRError> {
+        encode_mixed(&self.payload()?, version, err_correction_level)
+    }
+}
@@ -0,0 +1,85 @@
+use alloc::fmt;
+use alloc::string::String;
+
+use qr_encoder::cell::Color;
+use qr_encoder::config::EncodingMode;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QRError {
+    // the data does not fit in any symbol at the requested settings
+    DataTooLong { length: usize, capacity: usize },
+    // versions run from 1 to 40; signed so negative versions from the C and
+    // JS interfaces are reported as given
+    InvalidVersion(i64),
+    // a version that is not a number at all
+    UnparsableVersion(String),
+    // error correction levels are L, M, Q or H
+    InvalidECLevel(String),
+    // the segments need more data bits than the symbol has
+    SegmentsTooLong { bits: usize, capacity: usize },
+    // a byte that cannot be encoded in the segment's mode
+    InvalidCharacter { mode: EncodingMode, byte: u8 },
+    // ECI designators run from 0 to 999999
+    InvalidEci(u32),
+    // an unknown shape or a malformed colour
+    InvalidStyle(String),
+    // a foreground colour too close in brightness to the background
+    LowContrast { foreground: Color, background: Color },
+    // the finder patterns no longer show the 1:1:3:1:1 ratio scanners look for
+    FinderDistorted,
+    // a symbol that could not be read back
+    Undecodable(String),
+    // a content type field that is missing or out of range
+    InvalidPayload(String),
+    // a signed payload whose signature doesn't match its data and key
+    InvalidSignature,
+    // a stress test damage region that doesn't parse
+    InvalidDamage(String),
+    // printer output past what the printer or its command can take
+    PrinterLimit(String)
+}
+
+impl fmt::Display for QRError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            QRError::DataTooLong { length, capacity } => {
+                write!(f, "data is {} characters long but at most {} fit", length, capacity)
+            },
+            QRError::InvalidVersion(version) => {
+                write!(f, "version {} is not between 1 and 40", version)
+            },
+            QRError::UnparsableVersion(ref version) => {
+                write!(f, "version {:?} is not a number between 1 and 40", version)
+            },
+            QRError::InvalidECLevel(ref level) => {
+                write!(f, "error correction level {:?} is not one of L, M, Q or H", level)
+            },
+            QRError::SegmentsTooLong { bits, capacity } => {
+                write!(f, "data needs {} bits but at most {} fit", bits, capacity)
+            },
+            QRError::InvalidCharacter { mode, byte } => {
+                write!(f, "byte 0x{:02x} cannot be encoded in {:?} mode", byte, mode)
+            },
+            QRError::InvalidEci(designator) => {
+                write!(f, "ECI designator {} is not between 0 and 999999", designator)
+            },
+            QRError::InvalidStyle(ref reason) => write!(f, "{}", reason),
+            QRError::LowContrast { foreground, background } => {
+                write!(
+                    f, "foreground colour #{:02x}{:02x}{:02x} is too close in brightness to the background #{:02x}{:02x}{:02x}",
+                    foreground.r, foreground.g, foreground.b, background.r, background.g, background.b
+                )
+            },
+            QRError::FinderDistorted => {
+                write!(f, "the finder pattern style breaks the 1:1:3:1:1 ratio scanners look for")
+            },
+            QRError::Undecodable(ref reason) => write!(f, "the symbol could not be decoded: {}", reason),
+            QRError::InvalidPayload(ref reason) => write!(f, "{}", reason),
+            QRError::InvalidSignature => write!(f, "the signature does not match the data and key"),
+            QRError::InvalidDamage(ref region) => {
+                write!(f, "{:?} is not a damage region, expected random:N, burst:N or rect:ROW,COL,WIDTH,HEIGHT", region)
+            },
+            QRError::PrinterLimit(ref reason) => write!(f, "{}", reason)
+        }
+    }
+}
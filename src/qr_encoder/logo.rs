@@ -0,0 +1,167 @@
+use alloc::vec::Vec;
+
+use qr_encoder::cell::CellType;
+use qr_encoder::config::{CellTrack, ECLevel};
+use qr_encoder::error::QRError;
+use qr_encoder::qr::QR;
+use qr_encoder::util::codeword_info;
+
+// share of the correctable codewords a logo may use up, leaving the rest
+// for real world damage such as glare, dirt or a poor print
+pub const SAFE_EC_FRACTION: f32 = 0.6;
+
+// modules kept clear of the logo along each edge, covering the finder,
+// separator, timing, format and version areas
+const EDGE_MARGIN: usize = 9;
+
+// where the logo sits, in modules. `size` is the square that gets cleared,
+// which is the logo itself plus `margin` modules of quiet box on every side.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LogoArea {
+    pub row: usize,
+    pub col: usize,
+    pub size: usize,
+    pub margin: usize,
+    // codewords with at least one module under the cleared square
+    pub damaged_codewords: usize,
+    // the most of those that fall in any one error correction block
+    pub worst_block: usize,
+    // codewords each block can lose while leaving the safe share of its
+    // error correction
+    pub budget: usize
+}
+
+impl LogoArea {
+    // blocks are corrected on their own, so every one has to stay in budget
+    pub fn is_safe(&self) -> bool {
+        self.worst_block <= self.budget
+    }
+
+    pub fn logo_size(&self) -> usize {
+        self.size - self.margin * 2
+    }
+
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        row >= self.row && row < self.row + self.size && col >= self.col && col < self.col + self.size
+    }
+}
+
+// logos need the higher error correction levels, so L and M are raised to H
+pub fn logo_ec_level(level: ECLevel) -> ECLevel {
+    match level {
+        ECLevel::Low | ECLevel::Medium => ECLevel::High,
+        level => level
+    }
+}
+
+// number of codewords of each block a logo may obscure: each lost codeword
+// takes two of the block's error correction codewords to correct, and only
+// the safe share of those is used
+pub fn ec_budget(version: usize, level: &ECLevel) -> usize {
+    let properties = codeword_info(version, level);
+    let correctable = properties.ecc_codeword_count / properties.block_count / 2;
+    (correctable as f32 * SAFE_EC_FRACTION) as usize
+}
+
+// the codeword each module was placed from, if any, as the symbol is printed
+fn module_codewords(qr: &QR, tracker: &CellTrack) -> Vec<Option<usize>> {
+    let width = qr.width();
+    let mirrored = qr.mirrored;
+    let qr = &qr.restore();
+    let mut codewords = vec![None; qr.body.len()];
+    let CellTrack(_, ref cw_order, ref point_order) = *tracker;
+    for (codeword, idx) in cw_order.iter().zip(point_order.iter()) {
+        if let CellType::Message = qr.body[*idx].module_type {
+            let (row, col) = (idx / width, idx % width);
+            let col = if mirrored { width - 1 - col } else { col };
+            codewords[row * width + col] = Some(*codeword);
+        }
+    }
+    codewords
+}
+
+// a square of `size` modules in the middle of the symbol, cleared of data.
+// Function patterns inside it, such as alignment patterns, are left showing
+// and don't count against the budget. The square is cut down to the symbol
+// if it is wider, as is the margin if it leaves no room.
+pub fn centred_area(qr: &QR, tracker: &CellTrack, version: usize, size: usize, margin: usize) -> LogoArea {
+    let width = qr.width();
+    let size = size.min(width);
+    let margin = margin.min(size / 2);
+    let start = (width - size) / 2;
+    let codewords = module_codewords(qr, tracker);
+
+    let mut damaged: Vec<usize> = vec![];
+    for row in start..(start + size) {
+        for col in start..(start + size) {
+            if let Some(codeword) = codewords[row * width + col] {
+                if !damaged.contains(&codeword) {
+                    damaged.push(codeword);
+                }
+            }
+        }
+    }
+
+    let properties = codeword_info(version, &qr.err_correction_level);
+    let blocks = properties.interleaved_blocks();
+    let mut per_block = vec![0; properties.block_count];
+    for codeword in damaged.iter() {
+        per_block[blocks[*codeword]] += 1;
+    }
+
+    LogoArea {
+        row: start,
+        col: start,
+        size,
+        margin,
+        damaged_codewords: damaged.len(),
+        worst_block: per_block.iter().cloned().max().unwrap_or(0),
+        budget: ec_budget(version, &qr.err_correction_level)
+    }
+}
+
+// the largest logo square that fits inside the symbol's edges, centred on the
+// symbol, whose size (plus its quiet box) has the same parity as the symbol
+pub fn max_logo_size(qr: &QR, margin: usize) -> usize {
+    let width = qr.width();
+    if width < EDGE_MARGIN * 2 + margin * 2 + 1 {
+        return 0;
+    }
+    width - EDGE_MARGIN * 2 - margin * 2
+}
+
+// the logo area for a logo `size` modules across, or the largest one that
+// stays within the error correction budget when no size is given. When none
+// does the area is empty, with a `logo_size` of 0. Fails when the quiet box
+// alone is too wide for the symbol.
+pub fn logo_area(qr: &QR, tracker: &CellTrack, version: usize, size: Option<usize>, margin: usize) -> Result<LogoArea, QRError> {
+    let width = qr.width();
+    let largest = max_logo_size(qr, margin);
+    if largest == 0 {
+        return Err(QRError::InvalidStyle(format!(
+            "a logo box of {} modules leaves no room for a logo in a symbol {} modules across", margin, width
+        )));
+    }
+
+    if let Some(size) = size {
+        // keep the square centred by matching the symbol's parity
+        let mut size = if size > largest { largest } else { size };
+        if (size + margin * 2) % 2 != width % 2 && size > 0 {
+            size -= 1;
+        }
+        return Ok(centred_area(qr, tracker, version, size + margin * 2, margin));
+    }
+
+    let mut best = centred_area(qr, tracker, version, 0, 0);
+    let mut size = width % 2;
+    while size <= largest {
+        let area = centred_area(qr, tracker, version, size + margin * 2, margin);
+        if !area.is_safe() {
+            break;
+        }
+        best = area;
+        size += 2;
+    }
+
+    Ok(best)
+}
@@ -2,3 +2,33 @@ pub mod cell;
 pub mod util;
 pub mod qr;
 pub mod config;
+pub mod capacity;
+pub mod bits;
+pub mod segment;
+pub mod error;
+pub mod version;
+pub mod svg;
+pub mod text;
+pub mod style;
+pub mod logo;
+pub mod multiplex;
+pub mod decode;
+pub mod stress;
+pub mod payload;
+pub mod payment;
+pub mod emv;
+pub mod otp;
+pub mod compact;
+#[cfg(feature = "signing")]
+pub mod signed;
+pub mod pdf;
+pub mod printer;
+pub mod sheet;
+#[cfg(feature = "image")]
+pub mod raster;
+pub mod debug;
+pub mod trace;
+#[cfg(feature = "cli")]
+pub mod cli;
+#[cfg(feature = "cli")]
+pub mod serve;
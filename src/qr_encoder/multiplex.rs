@@ -0,0 +1,97 @@
+use alloc::vec::Vec;
+
+use qr_encoder::cell::{Cell, Color};
+use qr_encoder::config::{QRConfig, ECLevel};
+use qr_encoder::error::QRError;
+use qr_encoder::qr::QR;
+use qr_encoder::version::Version;
+
+// a channel is dark where its message has a dark module, so the red channel
+// carries the first message, green the second and blue the third
+const LIGHT: u32 = 255;
+
+// encodes three messages at the same version, level and mask, so the function
+// patterns line up, and packs them into one symbol with a message per colour
+// channel. Modules come out in one of 8 colours: black where all three are
+// dark, white where all three are light.
+pub fn encode_multiplexed(payloads: [&[u8]; 3], version: Option<Version>, err_correction_level: ECLevel) -> Result<QR, QRError> {
+    // the smallest version that holds all three, unless one was given
+    let version = match version {
+        Some(version) => version,
+        None => {
+            let mut largest = Version::new(Version::MIN).unwrap();
+            for payload in payloads.iter() {
+                let config = QRConfig::from_data(payload.to_vec(), None, err_correction_level)?;
+                let version = Version::new(config.version).unwrap();
+                if version > largest {
+                    largest = version;
+                }
+            }
+            largest
+        }
+    };
+
+    // pick the mask that does best across all three
+    let mut totals = [0usize; 8];
+    for payload in payloads.iter() {
+        let mut config = QRConfig::from_data(payload.to_vec(), Some(version), err_correction_level)?;
+        let (_, _, trace) = config.gen_qr_code_with_trace();
+        for penalty in trace.mask_penalties.iter() {
+            totals[penalty.pattern] += penalty.total;
+        }
+    }
+    let mask = (0..8).min_by_key(|pattern| totals[*pattern]).unwrap();
+
+    let mut channels: Vec<QR> = vec![];
+    for payload in payloads.iter() {
+        let mut config = QRConfig::from_data(payload.to_vec(), Some(version), err_correction_level)?;
+        config.fixed_mask = Some(mask);
+        let (qr, _) = config.gen_qr_code();
+        channels.push(qr);
+    }
+
+    Ok(compose(&channels[0], &channels[1], &channels[2]))
+}
+
+// packs three monochrome symbols into the red, green and blue channels of
+// one. Only `encode_multiplexed` calls this, with symbols it made at the same
+// version, so they are always the same size.
+fn compose(red: &QR, green: &QR, blue: &QR) -> QR {
+    let channel = |qr: &QR, idx: usize| if qr.body[idx].is_black() { 0 } else { LIGHT };
+    let body: Vec<Cell> = red.body.iter().enumerate()
+        .map(|(idx, cell)| Cell {
+            module_type: cell.module_type.clone(),
+            value: cell.value,
+            point: cell.point,
+            color: Color { r: channel(red, idx), g: channel(green, idx), b: channel(blue, idx) }
+        })
+        .collect();
+
+    let mut qr = QR::new(body, red.err_correction_level);
+    qr.inverted = red.inverted;
+    qr.mirrored = red.mirrored;
+    qr
+}
+
+// recovers the monochrome symbols from the red, green and blue channels. A
+// channel counts as dark below half intensity.
+pub fn split(qr: &QR) -> [QR; 3] {
+    let black = Color { r: 0, g: 0, b: 0 };
+    let white = Color { r: LIGHT, g: LIGHT, b: LIGHT };
+    let channel = |pick: &dyn Fn(&Color) -> u32| {
+        let body = qr.body.iter()
+            .map(|cell| Cell {
+                module_type: cell.module_type.clone(),
+                value: cell.value,
+                point: cell.point,
+                color: if pick(&cell.color) < LIGHT / 2 { black } else { white }
+            })
+            .collect();
+        let mut channel = QR::new(body, qr.err_correction_level);
+        channel.inverted = qr.inverted;
+        channel.mirrored = qr.mirrored;
+        channel
+    };
+
+    [channel(&|c| c.r), channel(&|c| c.g), channel(&|c| c.b)]
+}
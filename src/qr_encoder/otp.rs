@@ -0,0 +1,211 @@
+use alloc::fmt;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ptr;
+
+use qr_encoder::config::{QRConfig, ECLevel};
+use qr_encoder::error::QRError;
+use qr_encoder::payload::{Payload, encode_mixed, percent_encode};
+use qr_encoder::version::Version;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+// RFC 4226 asks for shared secrets of at least 128 bits
+const MIN_SECRET_BYTES: usize = 16;
+
+fn invalid(reason: String) -> QRError {
+    QRError::InvalidPayload(reason)
+}
+
+// RFC 4648 Base32 without the `=` padding, which authenticator apps leave off
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    encoded
+}
+
+// overwrites bytes that held a secret, with volatile writes so the clearing
+// isn't optimised away
+pub fn clear_bytes(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        unsafe { ptr::write_volatile(byte, 0) };
+    }
+}
+
+// overwrites a string that held a secret, leaving it empty
+pub fn clear_string(text: &mut String) {
+    // zero bytes are valid UTF-8, so the string stays well formed throughout
+    clear_bytes(unsafe { text.as_bytes_mut() });
+    text.clear();
+}
+
+// reads Base32 in either case, ignoring spaces and padding. The output is
+// allocated at its full size up front, so growing it leaves no stray copies
+// of a secret behind.
+pub fn base32_decode(encoded: &str) -> Result<Vec<u8>, QRError> {
+    let mut data = Vec::with_capacity(encoded.len() * 5 / 8 + 1);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in encoded.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = match BASE32_ALPHABET.iter().position(|&a| a as char == c.to_ascii_uppercase()) {
+            Some(value) => value as u32,
+            None => return Err(invalid(format!("{:?} is not a Base32 character", c)))
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(data)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OtpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512
+}
+
+impl OtpAlgorithm {
+    fn name(&self) -> &'static str {
+        match *self {
+            OtpAlgorithm::Sha1 => "SHA1",
+            OtpAlgorithm::Sha256 => "SHA256",
+            OtpAlgorithm::Sha512 => "SHA512"
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OtpKind {
+    // time based, with a new code every `period` seconds
+    Totp { period: u32 },
+    // counter based, starting from `counter`
+    Hotp { counter: u64 }
+}
+
+// an `otpauth://` URI for enrolling an authenticator app. The secret is
+// kept out of `Debug` output and overwritten when the value is dropped. It
+// isn't `Clone`, so there is only ever the one copy to clear.
+pub struct OtpAuth {
+    pub kind: OtpKind,
+    pub issuer: String,
+    pub account: String,
+    pub secret: Vec<u8>,
+    pub algorithm: OtpAlgorithm,
+    pub digits: u32
+}
+
+impl OtpAuth {
+    // a TOTP enrolment with the defaults every app supports: SHA-1, six
+    // digits and a 30 second period
+    pub fn totp(issuer: &str, account: &str, secret: &[u8]) -> OtpAuth {
+        OtpAuth {
+            kind: OtpKind::Totp { period: 30 },
+            issuer: String::from(issuer),
+            account: String::from(account),
+            secret: secret.to_vec(),
+            algorithm: OtpAlgorithm::Sha1,
+            digits: 6
+        }
+    }
+}
+
+impl fmt::Debug for OtpAuth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OtpAuth")
+            .field("kind", &self.kind)
+            .field("issuer", &self.issuer)
+            .field("account", &self.account)
+            .field("secret", &"<redacted>")
+            .field("algorithm", &self.algorithm)
+            .field("digits", &self.digits)
+            .finish()
+    }
+}
+
+impl Drop for OtpAuth {
+    fn drop(&mut self) {
+        clear_bytes(&mut self.secret);
+    }
+}
+
+impl Payload for OtpAuth {
+    // `otpauth://totp/Issuer:account?secret=...&issuer=Issuer`, with the
+    // algorithm, digits and period only written when they aren't the defaults
+    fn payload(&self) -> Result<String, QRError> {
+        if self.account.is_empty() {
+            return Err(invalid(String::from("an account name is required")));
+        }
+        // the colon separates the issuer from the account in the label
+        if self.issuer.contains(':') || self.account.contains(':') {
+            return Err(invalid(String::from("the issuer and account can't contain a colon")));
+        }
+        if self.secret.len() < MIN_SECRET_BYTES {
+            return Err(invalid(format!("the secret is {} bytes but needs at least {}", self.secret.len(), MIN_SECRET_BYTES)));
+        }
+        if !(6..=8).contains(&self.digits) {
+            return Err(invalid(format!("codes of {} digits aren't supported, only 6 to 8", self.digits)));
+        }
+
+        let (kind, counter) = match self.kind {
+            OtpKind::Totp { period: 0 } => return Err(invalid(String::from("the period must be at least a second"))),
+            OtpKind::Totp { period } => ("totp", if period != 30 { format!("&period={}", period) } else { String::new() }),
+            OtpKind::Hotp { counter } => ("hotp", format!("&counter={}", counter))
+        };
+        let label = if self.issuer.is_empty() {
+            percent_encode(&self.account, "@")
+        } else {
+            format!("{}:{}", percent_encode(&self.issuer, ""), percent_encode(&self.account, "@"))
+        };
+
+        let mut parameters = String::new();
+        if !self.issuer.is_empty() {
+            parameters.push_str(&format!("&issuer={}", percent_encode(&self.issuer, "")));
+        }
+        if self.algorithm != OtpAlgorithm::Sha1 {
+            parameters.push_str(&format!("&algorithm={}", self.algorithm.name()));
+        }
+        if self.digits != 6 {
+            parameters.push_str(&format!("&digits={}", self.digits));
+        }
+        parameters.push_str(&counter);
+
+        // sized up front, so the Base32 secret is never left behind in a
+        // buffer the string grew out of
+        let mut secret = base32_encode(&self.secret);
+        let mut payload = String::with_capacity(kind.len() + label.len() + secret.len() + parameters.len() + 20);
+        payload.push_str("otpauth://");
+        payload.push_str(kind);
+        payload.push('/');
+        payload.push_str(&label);
+        payload.push_str("?secret=");
+        payload.push_str(&secret);
+        payload.push_str(&parameters);
+        clear_string(&mut secret);
+        Ok(payload)
+    }
+
+    // the Base32 secret is upper case, so it goes in an alphanumeric segment.
+    // The URI is cleared once it is encoded.
+    fn config(&self, version: Option<Version>, err_correction_level: ECLevel) -> Result<QRConfig, QRError> {
+        let mut payload = self.payload()?;
+        let config = encode_mixed(&payload, version, err_correction_level);
+        clear_string(&mut payload);
+        config
+    }
+}
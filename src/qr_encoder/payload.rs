@@ -0,0 +1,461 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use qr_encoder::capacity::data_bit_capacity;
+use qr_encoder::config::{QRConfig, ECLevel};
+use qr_encoder::error::QRError;
+use qr_encoder::segment::{Segment, optimal_segments, segments_bit_length};
+use qr_encoder::version::Version;
+
+// vCard and iCalendar lines longer than this many bytes are folded
+const MAX_LINE_LENGTH: usize = 75;
+
+// a standard content type that scanners recognise and act on
+pub trait Payload {
+    // the text to put in the symbol
+    fn payload(&self) -> Result<String, QRError>;
+
+    // a config for the payload, in whichever mode encodes it in the fewest bits
+    fn config(&self, version: Option<Version>, err_correction_level: ECLevel) -> Result<QRConfig, QRError> {
+        encode_text(&self.payload()?, version, err_correction_level)
+    }
+}
+
+// a config for `text` as a single segment in its cheapest mode
+pub fn encode_text(text: &str, version: Option<Version>, err_correction_level: ECLevel) -> Result<QRConfig, QRError> {
+    QRConfig::from_segments(vec![Segment::cheapest(text.as_bytes())], version, err_correction_level)
+}
+
+// a config for `text` split into numeric, alphanumeric and byte segments
+// wherever switching mode saves bits, in the smallest version it fits unless
+// one is given
+pub fn encode_mixed(text: &str, version: Option<Version>, err_correction_level: ECLevel) -> Result<QRConfig, QRError> {
+    let data = text.as_bytes();
+    if let Some(version) = version {
+        return QRConfig::from_segments(optimal_segments(data, version.number()), Some(version), err_correction_level);
+    }
+
+    let mut bits = 0;
+    for version in Version::all() {
+        let segments = optimal_segments(data, version.number());
+        bits = segments_bit_length(&segments, version.number());
+        if bits <= data_bit_capacity(version, &err_correction_level) {
+            return QRConfig::from_segments(segments, Some(version), err_correction_level);
+        }
+    }
+    Err(QRError::SegmentsTooLong { bits, capacity: data_bit_capacity(Version::largest(), &err_correction_level) })
+}
+
+fn required(field: &str, value: &str) -> Result<(), QRError> {
+    if value.is_empty() {
+        return Err(QRError::InvalidPayload(format!("{} is required", field)));
+    }
+    Ok(())
+}
+
+// backslash escapes for the `WIFI:` and `MECARD:` formats, where `;`, `,`
+// and `:` separate fields
+fn escape_fields(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if let '\\' | ';' | ',' | ':' | '"' = c {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// text value escaping shared by vCard and iCalendar
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {},
+            _ => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+// a content line, folded so no line is longer than 75 bytes. Continuation
+// lines start with a space, and characters are never split.
+fn content_line(out: &mut String, line: &str) {
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            out.push_str("\r\n ");
+            length = 1;
+        }
+        out.push(c);
+        length += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+// percent-encodes everything but the unreserved characters and `keep`
+pub fn percent_encode(value: &str, keep: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        let c = byte as char;
+        if c.is_ascii_alphanumeric() || "-._~".contains(c) || keep.contains(c) {
+            encoded.push(c);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WifiSecurity {
+    Wpa,
+    Wep,
+    // an open network
+    None
+}
+
+// joins a Wi-Fi network: `WIFI:T:WPA;S:<ssid>;P:<password>;;`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Wifi {
+    pub ssid: String,
+    pub password: String,
+    pub security: WifiSecurity,
+    // the network doesn't broadcast its SSID
+    pub hidden: bool
+}
+
+impl Payload for Wifi {
+    fn payload(&self) -> Result<String, QRError> {
+        required("an SSID", &self.ssid)?;
+        let security = match self.security {
+            WifiSecurity::Wpa => "WPA",
+            WifiSecurity::Wep => "WEP",
+            WifiSecurity::None => "nopass"
+        };
+
+        let mut payload = format!("WIFI:T:{};S:{};", security, escape_fields(&self.ssid));
+        if self.security != WifiSecurity::None {
+            required("a password", &self.password)?;
+            payload.push_str(&format!("P:{};", escape_fields(&self.password)));
+        }
+        if self.hidden {
+            payload.push_str("H:true;");
+        }
+        payload.push(';');
+        Ok(payload)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Address {
+    pub street: String,
+    pub city: String,
+    pub region: String,
+    pub postcode: String,
+    pub country: String
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VCardVersion {
+    V3,
+    V4
+}
+
+// a person's details, written out as a vCard or the shorter MeCard
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Contact {
+    pub family_name: String,
+    pub given_name: String,
+    pub organisation: String,
+    pub title: String,
+    pub phones: Vec<String>,
+    pub emails: Vec<String>,
+    pub url: String,
+    pub address: Option<Address>,
+    pub note: String
+}
+
+impl Contact {
+    // the name as it is displayed, given name first
+    pub fn formatted_name(&self) -> String {
+        let mut name = self.given_name.clone();
+        if !name.is_empty() && !self.family_name.is_empty() {
+            name.push(' ');
+        }
+        name.push_str(&self.family_name);
+        name
+    }
+
+    pub fn vcard(&self, version: VCardVersion) -> VCard<'_> {
+        VCard { contact: self, version }
+    }
+
+    pub fn mecard(&self) -> MeCard<'_> {
+        MeCard { contact: self }
+    }
+}
+
+pub struct VCard<'a> {
+    pub contact: &'a Contact,
+    pub version: VCardVersion
+}
+
+impl<'a> Payload for VCard<'a> {
+    fn payload(&self) -> Result<String, QRError> {
+        let contact = self.contact;
+        required("a name", &contact.formatted_name())?;
+
+        let mut lines: Vec<String> = vec![
+            String::from("BEGIN:VCARD"),
+            String::from(match self.version {
+                VCardVersion::V3 => "VERSION:3.0",
+                VCardVersion::V4 => "VERSION:4.0"
+            }),
+            format!("N:{};{};;;", escape_text(&contact.family_name), escape_text(&contact.given_name)),
+            format!("FN:{}", escape_text(&contact.formatted_name()))
+        ];
+        // URI values such as URL are written as they are, text values escaped
+        let mut optional = |property: &str, value: &str, text: bool| {
+            if !value.is_empty() {
+                let value = if text { escape_text(value) } else { String::from(value) };
+                lines.push(format!("{}:{}", property, value));
+            }
+        };
+        optional("ORG", &contact.organisation, true);
+        optional("TITLE", &contact.title, true);
+        for phone in contact.phones.iter() {
+            match self.version {
+                VCardVersion::V3 => optional("TEL", phone, true),
+                VCardVersion::V4 => optional("TEL;VALUE=uri", &format!("tel:{}", phone), false)
+            }
+        }
+        for email in contact.emails.iter() {
+            optional("EMAIL", email, true);
+        }
+        optional("URL", &contact.url, false);
+        optional("NOTE", &contact.note, true);
+        if let Some(ref address) = contact.address {
+            lines.push(format!(
+                "ADR:;;{};{};{};{};{}",
+                escape_text(&address.street), escape_text(&address.city), escape_text(&address.region),
+                escape_text(&address.postcode), escape_text(&address.country)
+            ));
+        }
+        lines.push(String::from("END:VCARD"));
+
+        let mut payload = String::new();
+        for line in lines.iter() {
+            content_line(&mut payload, line);
+        }
+        Ok(payload)
+    }
+}
+
+// the compact contact format from NTT Docomo, understood by most scanners
+pub struct MeCard<'a> {
+    pub contact: &'a Contact
+}
+
+impl<'a> Payload for MeCard<'a> {
+    fn payload(&self) -> Result<String, QRError> {
+        let contact = self.contact;
+        required("a name", &contact.formatted_name())?;
+
+        let mut payload = String::from("MECARD:N:");
+        payload.push_str(&escape_fields(&contact.family_name));
+        if !contact.given_name.is_empty() {
+            payload.push(',');
+            payload.push_str(&escape_fields(&contact.given_name));
+        }
+        payload.push(';');
+
+        let mut optional = |field: &str, value: &str| {
+            if !value.is_empty() {
+                payload.push_str(&format!("{}:{};", field, escape_fields(value)));
+            }
+        };
+        for phone in contact.phones.iter() {
+            optional("TEL", phone);
+        }
+        for email in contact.emails.iter() {
+            optional("EMAIL", email);
+        }
+        optional("URL", &contact.url);
+        optional("NOTE", &contact.note);
+        if let Some(ref address) = contact.address {
+            // post office box, extended address, street, city, region, postcode, country
+            let parts = [&address.street, &address.city, &address.region, &address.postcode, &address.country];
+            let fields: Vec<String> = parts.iter().map(|part| escape_fields(part)).collect();
+            payload.push_str(&format!("ADR:,,{};", fields.join(",")));
+        }
+        payload.push(';');
+        Ok(payload)
+    }
+}
+
+// a point on the map: `geo:<latitude>,<longitude>[,<altitude>]`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Geo {
+    pub latitude: f64,
+    pub longitude: f64,
+    // metres above sea level
+    pub altitude: Option<f64>
+}
+
+impl Payload for Geo {
+    fn payload(&self) -> Result<String, QRError> {
+        if !(-90.0..=90.0).contains(&self.latitude) {
+            return Err(QRError::InvalidPayload(format!("latitude {} is not between -90 and 90", self.latitude)));
+        }
+        if !(-180.0..=180.0).contains(&self.longitude) {
+            return Err(QRError::InvalidPayload(format!("longitude {} is not between -180 and 180", self.longitude)));
+        }
+
+        Ok(match self.altitude {
+            Some(altitude) => format!("geo:{},{},{}", self.latitude, self.longitude, altitude),
+            None => format!("geo:{},{}", self.latitude, self.longitude)
+        })
+    }
+}
+
+// a text message ready to send: `SMSTO:<number>:<message>`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sms {
+    pub number: String,
+    pub message: String
+}
+
+impl Payload for Sms {
+    fn payload(&self) -> Result<String, QRError> {
+        let digits = self.number.strip_prefix('+').unwrap_or(&self.number);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(QRError::InvalidPayload(format!("{:?} is not a phone number", self.number)));
+        }
+        // the message runs to the end, so it needs no escaping
+        Ok(format!("SMSTO:{}:{}", self.number, self.message))
+    }
+}
+
+// an e-mail ready to send, as a `mailto:` URI
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Email {
+    pub to: String,
+    pub cc: Vec<String>,
+    pub subject: String,
+    pub body: String
+}
+
+impl Payload for Email {
+    fn payload(&self) -> Result<String, QRError> {
+        for address in Some(&self.to).into_iter().chain(self.cc.iter()) {
+            if !address.contains('@') {
+                return Err(QRError::InvalidPayload(format!("{:?} is not an e-mail address", address)));
+            }
+        }
+
+        let mut fields: Vec<String> = vec![];
+        if !self.cc.is_empty() {
+            let cc: Vec<String> = self.cc.iter().map(|address| percent_encode(address, "@")).collect();
+            fields.push(format!("cc={}", cc.join(",")));
+        }
+        if !self.subject.is_empty() {
+            fields.push(format!("subject={}", percent_encode(&self.subject, "")));
+        }
+        if !self.body.is_empty() {
+            // line breaks in the body are CRLF
+            let body = self.body.replace("\r\n", "\n").replace('\n', "\r\n");
+            fields.push(format!("body={}", percent_encode(&body, "")));
+        }
+
+        let mut payload = format!("mailto:{}", percent_encode(&self.to, "@"));
+        if !fields.is_empty() {
+            payload.push('?');
+            payload.push_str(&fields.join("&"));
+        }
+        Ok(payload)
+    }
+}
+
+// a calendar date and time, in UTC or the reader's local time
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub utc: bool
+}
+
+// days in a month of the Gregorian calendar, with February's leap day
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+impl DateTime {
+    // the iCalendar form, such as `20261019T093000Z`
+    fn to_ical(self) -> Result<String, QRError> {
+        if !(1..=12).contains(&self.month) || !(1..=days_in_month(self.year, self.month)).contains(&self.day)
+            || self.hour > 23 || self.minute > 59 || self.second > 60 {
+            return Err(QRError::InvalidPayload(format!("{:?} is not a valid date and time", self)));
+        }
+        Ok(format!(
+            "{:04}{:02}{:02}T{:02}{:02}{:02}{}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second,
+            if self.utc { "Z" } else { "" }
+        ))
+    }
+}
+
+// an iCalendar event that scanners offer to add to a calendar
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub summary: String,
+    pub start: DateTime,
+    pub end: Option<DateTime>,
+    pub location: String,
+    pub description: String
+}
+
+impl Payload for Event {
+    fn payload(&self) -> Result<String, QRError> {
+        required("a summary", &self.summary)?;
+
+        let mut lines: Vec<String> = vec![
+            String::from("BEGIN:VEVENT"),
+            format!("SUMMARY:{}", escape_text(&self.summary)),
+            format!("DTSTART:{}", self.start.to_ical()?)
+        ];
+        if let Some(end) = self.end {
+            if end.utc != self.start.utc || end < self.start {
+                return Err(QRError::InvalidPayload(String::from("the event ends before it starts")));
+            }
+            lines.push(format!("DTEND:{}", end.to_ical()?));
+        }
+        if !self.location.is_empty() {
+            lines.push(format!("LOCATION:{}", escape_text(&self.location)));
+        }
+        if !self.description.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape_text(&self.description)));
+        }
+        lines.push(String::from("END:VEVENT"));
+
+        let mut payload = String::new();
+        for line in lines.iter() {
+            content_line(&mut payload, line);
+        }
+        Ok(payload)
+    }
+}
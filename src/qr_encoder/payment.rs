@@ -0,0 +1,501 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use qr_encoder::cell::Color;
+use qr_encoder::config::{QRConfig, ECLevel};
+use qr_encoder::error::QRError;
+use qr_encoder::payload::Payload;
+use qr_encoder::segment::Segment;
+use qr_encoder::style::RoundedRect;
+use qr_encoder::version::Version;
+
+// EPC069-12 limits: the largest symbol scanners are asked to support, and
+// the longest payload that fits it at level M
+const EPC_MAX_VERSION: usize = 13;
+const EPC_MAX_BYTES: usize = 331;
+
+// Swiss QR-bill limits from the implementation guidelines
+const SWISS_MAX_VERSION: usize = 25;
+const SWISS_MAX_CHARACTERS: usize = 997;
+
+// the printed size of a QR-bill symbol, quiet zone not included, and of the
+// Swiss cross over its middle
+pub const SWISS_SYMBOL_MM: f32 = 46.0;
+pub const SWISS_CROSS_MM: f32 = 7.0;
+
+fn invalid(reason: String) -> QRError {
+    QRError::InvalidPayload(reason)
+}
+
+// the value of an alphanumeric string read as a number with letters
+// standing for 10 to 35, modulo 97, as used by IBANs and creditor references
+fn mod_97(value: &str) -> Option<u32> {
+    let mut remainder = 0;
+    for c in value.chars() {
+        let digit = c.to_digit(36)?;
+        let shifted = if digit < 10 { remainder * 10 } else { remainder * 100 };
+        remainder = (shifted + digit) % 97;
+    }
+    Some(remainder)
+}
+
+// whether the check digits after the first two characters are right: the
+// first four characters move to the end and the whole must be 1 modulo 97
+fn check_digits_valid(value: &str) -> bool {
+    if value.len() < 5 || !value.is_ascii() {
+        return false;
+    }
+    let rearranged = format!("{}{}", &value[4..], &value[..4]);
+    mod_97(&rearranged) == Some(1)
+}
+
+// an IBAN with its spaces taken out and letters in upper case
+pub fn compact_iban(iban: &str) -> String {
+    iban.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_ascii_uppercase()
+}
+
+// a country code, two check digits and up to 30 letters and digits that pass
+// the mod-97 check. Spaces are ignored.
+pub fn valid_iban(iban: &str) -> bool {
+    let iban = compact_iban(iban);
+    let bytes = iban.as_bytes();
+    (15..=34).contains(&bytes.len())
+        && bytes[..2].iter().all(|b| b.is_ascii_uppercase())
+        && bytes[2..4].iter().all(|b| b.is_ascii_digit())
+        && bytes[4..].iter().all(|b| b.is_ascii_alphanumeric())
+        && check_digits_valid(&iban)
+}
+
+// a bank code of four letters, a country code, a two character location
+// and an optional three character branch
+pub fn valid_bic(bic: &str) -> bool {
+    let bytes = bic.as_bytes();
+    (bytes.len() == 8 || bytes.len() == 11)
+        && bytes[..6].iter().all(|b| b.is_ascii_uppercase())
+        && bytes[6..].iter().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+}
+
+// an ISO 11649 structured creditor reference: `RF`, two check digits and up
+// to 21 letters and digits
+pub fn valid_creditor_reference(reference: &str) -> bool {
+    let reference = compact_iban(reference);
+    reference.starts_with("RF")
+        && (5..=25).contains(&reference.len())
+        && reference.bytes().all(|b| b.is_ascii_alphanumeric())
+        && check_digits_valid(&reference)
+}
+
+// an amount with at most two decimal places, from 0.01 to 999999999.99
+fn valid_amount(amount: &str) -> bool {
+    let mut parts = amount.splitn(2, '.');
+    let whole = parts.next().unwrap_or("");
+    let cents = parts.next().unwrap_or("");
+    if whole.is_empty() || whole.len() > 9 || cents.len() > 2 || amount.ends_with('.') {
+        return false;
+    }
+    if !whole.bytes().chain(cents.bytes()).all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    whole.bytes().chain(cents.bytes()).any(|b| b != b'0')
+}
+
+// a field that must fit on its line of the payload
+fn check_field(field: &str, value: &str, max: usize) -> Result<(), QRError> {
+    if value.contains('\n') || value.contains('\r') {
+        return Err(invalid(format!("the {} can't contain line breaks", field)));
+    }
+    let length = value.chars().count();
+    if length > max {
+        return Err(invalid(format!("the {} is {} characters long but at most {} are allowed", field, length, max)));
+    }
+    Ok(())
+}
+
+// what the payer quotes with the payment: a structured creditor reference
+// or free text, but not both
+#[derive(Clone, Debug, PartialEq)]
+pub enum Remittance {
+    None,
+    Reference(String),
+    Text(String)
+}
+
+// an EPC069-12 SEPA credit transfer, known as a GiroCode
+#[derive(Clone, Debug, PartialEq)]
+pub struct Epc {
+    // may be left empty for payments within the EEA
+    pub bic: String,
+    pub name: String,
+    pub iban: String,
+    // in euro, such as `12.30`; left for the payer to fill in when not set
+    pub amount: Option<String>,
+    // a four letter ISO 20022 purpose code
+    pub purpose: String,
+    pub remittance: Remittance,
+    // a note shown to the payer
+    pub information: String
+}
+
+impl Payload for Epc {
+    // the twelve lines of version 002 of the format, in UTF-8, with empty
+    // trailing lines left off
+    fn payload(&self) -> Result<String, QRError> {
+        if !self.bic.is_empty() && !valid_bic(&self.bic) {
+            return Err(invalid(format!("{:?} is not a BIC", self.bic)));
+        }
+        if self.name.is_empty() {
+            return Err(invalid(String::from("the beneficiary name is required")));
+        }
+        check_field("beneficiary name", &self.name, 70)?;
+        if !valid_iban(&self.iban) {
+            return Err(invalid(format!("{:?} is not a valid IBAN", self.iban)));
+        }
+        let amount = match self.amount {
+            Some(ref amount) if valid_amount(amount) => format!("EUR{}", amount),
+            Some(ref amount) => return Err(invalid(format!("{:?} is not an amount from 0.01 to 999999999.99 euro", amount))),
+            None => String::new()
+        };
+        if !self.purpose.is_empty() && (self.purpose.len() != 4 || !self.purpose.bytes().all(|b| b.is_ascii_uppercase())) {
+            return Err(invalid(format!("{:?} is not a four letter purpose code", self.purpose)));
+        }
+        let (reference, text) = match self.remittance {
+            Remittance::None => (String::new(), ""),
+            Remittance::Reference(ref reference) => {
+                check_field("creditor reference", reference, 35)?;
+                if !reference.starts_with("RF") {
+                    (reference.clone(), "")
+                } else if valid_creditor_reference(reference) {
+                    // written without the spaces it is usually printed with
+                    (compact_iban(reference), "")
+                } else {
+                    return Err(invalid(format!("{:?} fails the creditor reference check", reference)));
+                }
+            },
+            Remittance::Text(ref text) => {
+                check_field("remittance text", text, 140)?;
+                (String::new(), &text[..])
+            }
+        };
+        check_field("information", &self.information, 70)?;
+
+        let iban = compact_iban(&self.iban);
+        let mut lines: Vec<&str> = vec![
+            "BCD", "002", "1", "SCT", &self.bic, &self.name, &iban, &amount,
+            &self.purpose, &reference, text, &self.information
+        ];
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        let payload = lines.join("\n");
+
+        if payload.len() > EPC_MAX_BYTES {
+            return Err(invalid(format!("the payment is {} bytes long but at most {} are allowed", payload.len(), EPC_MAX_BYTES)));
+        }
+        Ok(payload)
+    }
+
+    // always byte mode at level M, and no larger than version 13
+    fn config(&self, version: Option<Version>, err_correction_level: ECLevel) -> Result<QRConfig, QRError> {
+        if err_correction_level != ECLevel::Medium {
+            return Err(invalid(String::from("EPC payments must use error correction level M")));
+        }
+        let payload = self.payload()?;
+        let config = QRConfig::from_segments(vec![Segment::byte(payload.as_bytes())], version, ECLevel::Medium)?;
+        if config.version > EPC_MAX_VERSION {
+            return Err(invalid(format!("EPC payments must fit version {} but this one needs version {}", EPC_MAX_VERSION, config.version)));
+        }
+        Ok(config)
+    }
+}
+
+// the check digit of a QR reference, from the recursive modulo 10 scheme
+fn mod_10_recursive(digits: &str) -> u32 {
+    const TABLE: [u32; 10] = [0, 9, 4, 6, 8, 2, 7, 1, 3, 5];
+    let carry = digits.chars()
+        .filter_map(|c| c.to_digit(10))
+        .fold(0, |carry, digit| TABLE[((carry + digit) % 10) as usize]);
+    (10 - carry) % 10
+}
+
+// a Swiss or Liechtenstein IBAN whose institution identifier falls in the
+// 30000 to 31999 range reserved for QR-IBANs
+pub fn valid_qr_iban(iban: &str) -> bool {
+    let iban = compact_iban(iban);
+    if !valid_iban(&iban) || !(iban.starts_with("CH") || iban.starts_with("LI")) || iban.len() != 21 {
+        return false;
+    }
+    match iban[4..9].parse::<u32>() {
+        Ok(iid) => (30000..=31999).contains(&iid),
+        Err(_) => false
+    }
+}
+
+// 27 digits, the last of them a recursive modulo 10 check digit
+pub fn valid_qr_reference(reference: &str) -> bool {
+    let reference: String = reference.chars().filter(|c| !c.is_whitespace()).collect();
+    reference.len() == 27
+        && reference.bytes().all(|b| b.is_ascii_digit())
+        && mod_10_recursive(&reference[..26]) == reference[26..].parse::<u32>().unwrap_or(10)
+}
+
+// a structured address, the only kind QR-bills accept
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SwissAddress {
+    pub name: String,
+    pub street: String,
+    pub building_number: String,
+    pub postcode: String,
+    pub town: String,
+    // two letter ISO 3166 country code
+    pub country: String
+}
+
+impl SwissAddress {
+    // the seven address lines, starting with the address type
+    fn lines(&self, party: &str) -> Result<Vec<String>, QRError> {
+        for &(field, value, max) in [
+            ("name", &self.name, 70), ("street", &self.street, 70), ("building number", &self.building_number, 16),
+            ("postcode", &self.postcode, 16), ("town", &self.town, 35)
+        ].iter() {
+            check_field(&format!("{} {}", party, field), value, max)?;
+        }
+        if self.name.is_empty() || self.postcode.is_empty() || self.town.is_empty() {
+            return Err(invalid(format!("the {} needs a name, postcode and town", party)));
+        }
+        if self.country.len() != 2 || !self.country.bytes().all(|b| b.is_ascii_uppercase()) {
+            return Err(invalid(format!("{:?} is not a two letter country code", self.country)));
+        }
+
+        Ok(vec![
+            String::from("S"), self.name.clone(), self.street.clone(), self.building_number.clone(),
+            self.postcode.clone(), self.town.clone(), self.country.clone()
+        ])
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SwissCurrency {
+    Chf,
+    Eur
+}
+
+// how the payment is referenced. A QR reference needs a QR-IBAN, the
+// others need an ordinary IBAN.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SwissReference {
+    Qr(String),
+    Creditor(String),
+    None
+}
+
+// a Swiss QR-bill payment part, written as an SPC version 2.0 payload
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwissBill {
+    pub iban: String,
+    pub creditor: SwissAddress,
+    // left for the payer to fill in when not set
+    pub amount: Option<String>,
+    pub currency: SwissCurrency,
+    pub debtor: Option<SwissAddress>,
+    pub reference: SwissReference,
+    pub message: String,
+    // structured billing information for the payer's software
+    pub billing: String
+}
+
+impl Payload for SwissBill {
+    fn payload(&self) -> Result<String, QRError> {
+        if !valid_iban(&self.iban) || !(compact_iban(&self.iban).starts_with("CH") || compact_iban(&self.iban).starts_with("LI")) {
+            return Err(invalid(format!("{:?} is not a valid Swiss or Liechtenstein IBAN", self.iban)));
+        }
+        let qr_iban = valid_qr_iban(&self.iban);
+        let (reference_type, reference) = match self.reference {
+            SwissReference::Qr(ref reference) => {
+                if !qr_iban {
+                    return Err(invalid(String::from("a QR reference needs a QR-IBAN")));
+                }
+                if !valid_qr_reference(reference) {
+                    return Err(invalid(format!("{:?} is not a valid QR reference", reference)));
+                }
+                ("QRR", reference.chars().filter(|c| !c.is_whitespace()).collect())
+            },
+            SwissReference::Creditor(ref reference) => {
+                if qr_iban {
+                    return Err(invalid(String::from("a QR-IBAN needs a QR reference")));
+                }
+                if !valid_creditor_reference(reference) {
+                    return Err(invalid(format!("{:?} is not a valid creditor reference", reference)));
+                }
+                ("SCOR", compact_iban(reference))
+            },
+            SwissReference::None => {
+                if qr_iban {
+                    return Err(invalid(String::from("a QR-IBAN needs a QR reference")));
+                }
+                ("NON", String::new())
+            }
+        };
+        let amount = match self.amount {
+            // always written with two decimal places
+            Some(ref amount) if valid_amount(amount) => match amount.find('.') {
+                Some(point) => format!("{}{}", amount, &"00"[(amount.len() - point - 1)..]),
+                None => format!("{}.00", amount)
+            },
+            Some(ref amount) => return Err(invalid(format!("{:?} is not an amount from 0.01 to 999999999.99", amount))),
+            None => String::new()
+        };
+        check_field("message", &self.message, 140)?;
+        check_field("billing information", &self.billing, 140)?;
+        if self.message.chars().count() + self.billing.chars().count() > 140 {
+            return Err(invalid(String::from("the message and billing information can be at most 140 characters together")));
+        }
+
+        let mut lines: Vec<String> = vec![String::from("SPC"), String::from("0200"), String::from("1"), compact_iban(&self.iban)];
+        lines.extend(self.creditor.lines("creditor")?);
+        // the ultimate creditor is reserved for future use and left empty
+        lines.extend(vec![String::new(); 7]);
+        lines.push(amount);
+        lines.push(String::from(match self.currency {
+            SwissCurrency::Chf => "CHF",
+            SwissCurrency::Eur => "EUR"
+        }));
+        match self.debtor {
+            Some(ref debtor) => lines.extend(debtor.lines("debtor")?),
+            None => lines.extend(vec![String::new(); 7])
+        }
+        lines.push(String::from(reference_type));
+        lines.push(reference);
+        lines.push(self.message.clone());
+        lines.push(String::from("EPD"));
+        if !self.billing.is_empty() {
+            lines.push(self.billing.clone());
+        }
+        let payload = lines.join("\n");
+
+        let length = payload.chars().count();
+        if length > SWISS_MAX_CHARACTERS {
+            return Err(invalid(format!("the bill is {} characters long but at most {} are allowed", length, SWISS_MAX_CHARACTERS)));
+        }
+        Ok(payload)
+    }
+
+    // always byte mode at level M, and no larger than version 25
+    fn config(&self, version: Option<Version>, err_correction_level: ECLevel) -> Result<QRConfig, QRError> {
+        if err_correction_level != ECLevel::Medium {
+            return Err(invalid(String::from("Swiss QR-bills must use error correction level M")));
+        }
+        swiss_config(&self.payload()?, version)
+    }
+}
+
+fn swiss_config(payload: &str, version: Option<Version>) -> Result<QRConfig, QRError> {
+    let config = QRConfig::from_segments(vec![Segment::byte(payload.as_bytes())], version, ECLevel::Medium)?;
+    if config.version > SWISS_MAX_VERSION {
+        return Err(invalid(format!("Swiss QR-bills must fit version {} but this one needs version {}", SWISS_MAX_VERSION, config.version)));
+    }
+    Ok(config)
+}
+
+// the seven lines of an address, with `None` for one left empty
+fn parse_swiss_address(lines: &[&str], party: &str) -> Result<Option<SwissAddress>, QRError> {
+    match lines[0] {
+        "" if lines.iter().all(|line| line.is_empty()) => Ok(None),
+        "S" => Ok(Some(SwissAddress {
+            name: String::from(lines[1]),
+            street: String::from(lines[2]),
+            building_number: String::from(lines[3]),
+            postcode: String::from(lines[4]),
+            town: String::from(lines[5]),
+            country: String::from(lines[6])
+        })),
+        kind => Err(invalid(format!("the {} address type {:?} is not S", party, kind)))
+    }
+}
+
+impl SwissBill {
+    // reads an SPC payload back, checking it as `payload` would. Up to two
+    // alternative procedure lines may follow the billing information; they
+    // are checked for length and otherwise left out.
+    pub fn parse(payload: &str) -> Result<SwissBill, QRError> {
+        let lines: Vec<&str> = payload.split('\n').map(|line| line.trim_end_matches('\r')).collect();
+        if lines.len() < 31 || lines.len() > 34 {
+            return Err(invalid(format!("an SPC payload has 31 to 34 lines but this one has {}", lines.len())));
+        }
+        if lines[..3] != ["SPC", "0200", "1"] {
+            return Err(invalid(String::from("the payload doesn't start with the SPC 0200 header")));
+        }
+        if lines[30] != "EPD" {
+            return Err(invalid(String::from("the payload doesn't end its payment part with EPD")));
+        }
+        for (i, procedure) in lines.iter().enumerate().skip(32) {
+            check_field(&format!("alternative procedure {}", i - 31), procedure, 100)?;
+        }
+
+        let creditor = match parse_swiss_address(&lines[4..11], "creditor")? {
+            Some(creditor) => creditor,
+            None => return Err(invalid(String::from("the creditor address is missing")))
+        };
+        if lines[11..18].iter().any(|line| !line.is_empty()) {
+            return Err(invalid(String::from("the ultimate creditor is reserved and must be left empty")));
+        }
+        let currency = match lines[19] {
+            "CHF" => SwissCurrency::Chf,
+            "EUR" => SwissCurrency::Eur,
+            currency => return Err(invalid(format!("currency {:?} is not CHF or EUR", currency)))
+        };
+        let reference = match (lines[27], lines[28]) {
+            ("QRR", reference) => SwissReference::Qr(String::from(reference)),
+            ("SCOR", reference) => SwissReference::Creditor(String::from(reference)),
+            ("NON", "") => SwissReference::None,
+            ("NON", _) => return Err(invalid(String::from("a reference type of NON can't have a reference"))),
+            (kind, _) => return Err(invalid(format!("reference type {:?} is not QRR, SCOR or NON", kind)))
+        };
+
+        let bill = SwissBill {
+            iban: String::from(lines[3]),
+            creditor,
+            amount: if lines[18].is_empty() { None } else { Some(String::from(lines[18])) },
+            currency,
+            debtor: parse_swiss_address(&lines[20..27], "debtor")?,
+            reference,
+            message: String::from(lines[29]),
+            billing: String::from(lines.get(31).cloned().unwrap_or(""))
+        };
+        bill.payload()?;
+        Ok(bill)
+    }
+}
+
+// a config for an SPC payload made elsewhere, encoded as it is once it
+// checks out as a QR-bill
+pub fn swiss_bill_config(payload: &[u8], version: Option<Version>) -> Result<QRConfig, QRError> {
+    let text = core::str::from_utf8(payload)
+        .map_err(|_| invalid(String::from("Swiss QR-bills must be UTF-8")))?;
+    SwissBill::parse(text)?;
+    let length = text.chars().count();
+    if length > SWISS_MAX_CHARACTERS {
+        return Err(invalid(format!("the bill is {} characters long but at most {} are allowed", length, SWISS_MAX_CHARACTERS)));
+    }
+    swiss_config(text, version)
+}
+
+// the Swiss cross for the middle of a symbol `width` modules across, in
+// module units, to be drawn in order: a white square, the black square
+// inside it and the two white bars of the cross. The proportions follow
+// the Swiss flag.
+pub fn swiss_cross(width: usize) -> [(RoundedRect, Color); 4] {
+    let size = width as f32 * SWISS_CROSS_MM / SWISS_SYMBOL_MM;
+    let centre = width as f32 / 2.0;
+    let square = |side: f32| RoundedRect { x: centre - side / 2.0, y: centre - side / 2.0, width: side, height: side, radii: [0.0; 4] };
+    let black = size * 6.0 / 7.0;
+    let (arm_width, arm_length) = (black * 6.0 / 32.0, black * 20.0 / 32.0);
+    let bar = |width: f32, height: f32| RoundedRect { x: centre - width / 2.0, y: centre - height / 2.0, width, height, radii: [0.0; 4] };
+    let white = Color { r: 255, g: 255, b: 255 };
+
+    [
+        (square(size), white),
+        (square(black), Color { r: 0, g: 0, b: 0 }),
+        (bar(arm_length, arm_width), white),
+        (bar(arm_width, arm_length), white)
+    ]
+}
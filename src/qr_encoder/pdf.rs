@@ -0,0 +1,170 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use qr_encoder::cell::Color;
+use qr_encoder::payment::{swiss_cross, SWISS_SYMBOL_MM};
+use qr_encoder::qr::QR;
+
+// PDF user space units are points, 72 to the inch
+const POINTS_PER_MM: f32 = 72.0 / 25.4;
+
+const WHITE: Color = Color { r: 255, g: 255, b: 255 };
+
+// advance widths of Helvetica's printable ASCII characters, from space to
+// `~`, in thousandths of the font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584
+];
+
+// the width in millimetres of `text` set in Helvetica at `size` points. Other
+// characters are taken to be as wide as a digit.
+pub fn text_width(text: &str, size: f32) -> f32 {
+    let thousandths: u32 = text.chars()
+        .map(|c| match c {
+            ' '..='~' => HELVETICA_WIDTHS[c as usize - 32] as u32,
+            _ => 556
+        })
+        .sum();
+    thousandths as f32 / 1000.0 * size / POINTS_PER_MM
+}
+
+// a page of a PDF document, drawn in millimetres from the top left corner
+pub struct PdfPage {
+    pub width: f32,
+    pub height: f32,
+    content: String,
+    fill: Option<Color>
+}
+
+impl PdfPage {
+    pub fn new(width: f32, height: f32) -> PdfPage {
+        PdfPage { width, height, content: String::new(), fill: None }
+    }
+
+    fn set_fill(&mut self, color: Color) {
+        if self.fill != Some(color) {
+            self.content.push_str(&format!(
+                "{:.3} {:.3} {:.3} rg\n",
+                color.r as f32 / 255.0, color.g as f32 / 255.0, color.b as f32 / 255.0
+            ));
+            self.fill = Some(color);
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        self.set_fill(color);
+        // PDF's origin is the bottom left corner
+        self.content.push_str(&format!(
+            "{:.3} {:.3} {:.3} {:.3} re f\n",
+            x * POINTS_PER_MM, (self.height - y - height) * POINTS_PER_MM, width * POINTS_PER_MM, height * POINTS_PER_MM
+        ));
+    }
+
+    // writes a line of Helvetica `size` points high with its baseline starting
+    // at `x`, `y`. Characters outside Latin-1 are written as `?`.
+    pub fn text(&mut self, x: f32, y: f32, size: f32, text: &str, color: Color) {
+        self.set_fill(color);
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '(' | ')' | '\\' => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                },
+                ' '..='~' => escaped.push(c),
+                // WinAnsiEncoding agrees with Latin-1 from 0xA0 up
+                '\u{a0}'..='\u{ff}' => escaped.push_str(&format!("\\{:03o}", c as u32)),
+                _ => escaped.push('?')
+            }
+        }
+        self.content.push_str(&format!(
+            "BT /F1 {:.3} Tf {:.3} {:.3} Td ({}) Tj ET\n",
+            size, x * POINTS_PER_MM, (self.height - y) * POINTS_PER_MM, escaped
+        ));
+    }
+
+    // draws the symbol's modules with its top left corner at `x`, `y`, each
+    // module `module_size` millimetres across. Runs of modules in a row are
+    // drawn as one rectangle; modules in the `background` colour are left unpainted.
+    pub fn draw_symbol(&mut self, qr: &QR, x: f32, y: f32, module_size: f32, background: Color) {
+        let width = qr.width();
+        for row in 0..width {
+            let mut col = 0;
+            while col < width {
+                let color = qr.body[row * width + col].color;
+                let start = col;
+                while col < width && qr.body[row * width + col].color == color {
+                    col += 1;
+                }
+                if color != background {
+                    let left = x + start as f32 * module_size;
+                    let top = y + row as f32 * module_size;
+                    self.fill_rect(left, top, (col - start) as f32 * module_size, module_size, color);
+                }
+            }
+        }
+    }
+}
+
+// writes the pages out as a PDF document
+pub fn write_pdf(pages: &[PdfPage]) -> Vec<u8> {
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets: Vec<usize> = vec![];
+    let mut object = |out: &mut String, body: String| {
+        offsets.push(out.len());
+        out.push_str(&format!("{} 0 obj\n{}\nendobj\n", offsets.len(), body));
+    };
+
+    // the catalog and page tree come first, then a page and its content for each page
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", 3 + i * 2)).collect();
+    object(&mut out, String::from("<< /Type /Catalog /Pages 2 0 R >>"));
+    object(&mut out, format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()));
+    for (i, page) in pages.iter().enumerate() {
+        object(&mut out, format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.3} {:.3}] /Resources << /Font << /F1 << /Type /Font \
+/Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >> >> >> /Contents {} 0 R >>",
+            page.width * POINTS_PER_MM, page.height * POINTS_PER_MM, 4 + i * 2
+        ));
+        object(&mut out, format!("<< /Length {} >>\nstream\n{}endstream", page.content.len(), page.content));
+    }
+
+    let xref = out.len();
+    out.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1));
+    for offset in offsets.iter() {
+        out.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    out.push_str(&format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", offsets.len() + 1, xref));
+    out.into_bytes()
+}
+
+// the symbol on a page of its own, each module `module_size` millimetres
+// across, with `quiet_zone` modules of padding. An inverted symbol gets a
+// dark quiet zone.
+pub fn to_pdf(qr: &QR, module_size: f32, quiet_zone: usize) -> Vec<u8> {
+    let size = (qr.width() + quiet_zone * 2) as f32 * module_size;
+    let mut page = PdfPage::new(size, size);
+    let background = if qr.inverted { Color { r: 0, g: 0, b: 0 } } else { WHITE };
+    if qr.inverted {
+        page.fill_rect(0.0, 0.0, size, size, background);
+    }
+    let offset = quiet_zone as f32 * module_size;
+    page.draw_symbol(qr, offset, offset, module_size, background);
+    write_pdf(&[page])
+}
+
+// a Swiss QR-bill symbol on a page of its printed size of 46mm, without a
+// quiet zone, with the Swiss cross over its middle
+pub fn to_swiss_pdf(qr: &QR) -> Vec<u8> {
+    let module_size = SWISS_SYMBOL_MM / qr.width() as f32;
+    let mut page = PdfPage::new(SWISS_SYMBOL_MM, SWISS_SYMBOL_MM);
+    page.draw_symbol(qr, 0.0, 0.0, module_size, WHITE);
+    for &(ref rect, color) in swiss_cross(qr.width()).iter() {
+        page.fill_rect(rect.x * module_size, rect.y * module_size, rect.width * module_size, rect.height * module_size, color);
+    }
+    write_pdf(&[page])
+}
@@ -0,0 +1,116 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use qr_encoder::config::ECLevel;
+use qr_encoder::error::QRError;
+use qr_encoder::qr::QR;
+
+// ESC/POS command bytes
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+
+// the most printer dots a module, the same as the largest `^BQ`
+// magnification. A version 40 symbol is then 1850 dots across, over 9 inches
+// at 203 dpi.
+pub const MAX_DOT_SIZE: usize = 10;
+
+// the print head of an 80 mm receipt printer at 203 dpi
+pub const DEFAULT_HEAD_DOTS: usize = 576;
+
+fn check_dot_size(dot_size: usize) -> Result<(), QRError> {
+    if !(1..=MAX_DOT_SIZE).contains(&dot_size) {
+        return Err(QRError::PrinterLimit(format!("dot size {} is not between 1 and {}", dot_size, MAX_DOT_SIZE)));
+    }
+    Ok(())
+}
+
+// the symbol as a 1 bit bitmap, `dot_size` printer dots a module with
+// `quiet_zone` modules of padding. Rows are packed eight dots to a byte, most
+// significant bit first, with 1 for a dot that is printed. Returns the bytes
+// in a row, the number of rows and the bitmap.
+fn bitmap(qr: &QR, dot_size: usize, quiet_zone: usize) -> (usize, usize, Vec<u8>) {
+    let width = qr.width();
+    let dots = (width + quiet_zone * 2) * dot_size;
+    let row_bytes = dots.div_ceil(8);
+    let mut bits = vec![0; row_bytes * dots];
+
+    for y in 0..dots {
+        for x in 0..dots {
+            let (row, col) = (y / dot_size, x / dot_size);
+            let inside = row >= quiet_zone && col >= quiet_zone && row < width + quiet_zone && col < width + quiet_zone;
+            // an inverted symbol has a dark quiet zone
+            let dark = if inside { qr.is_dark(row - quiet_zone, col - quiet_zone) } else { qr.inverted };
+            if dark {
+                bits[y * row_bytes + x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+    }
+    (row_bytes, dots, bits)
+}
+
+// a ZPL label with the symbol as a `^GF` graphic field, so the printer draws
+// exactly the modules this encoder chose
+pub fn to_zpl(qr: &QR, dot_size: usize, quiet_zone: usize) -> Result<String, QRError> {
+    check_dot_size(dot_size)?;
+    let (row_bytes, rows, bits) = bitmap(qr, dot_size, quiet_zone);
+    let mut hex = String::with_capacity(bits.len() * 2);
+    for byte in bits.iter() {
+        hex.push_str(&format!("{:02X}", byte));
+    }
+    Ok(format!("^XA\n^FO0,0^GFA,{0},{0},{1},{2}^FS\n^XZ\n", row_bytes * rows, row_bytes, hex))
+}
+
+// a ZPL label that has the printer encode the message itself with `^BQ`,
+// each module `magnification` dots across. The printer picks the version and
+// mask, so the symbol may differ from this encoder's.
+pub fn to_zpl_native(message: &[u8], err_correction_level: ECLevel, magnification: usize) -> Result<String, QRError> {
+    if !(1..=MAX_DOT_SIZE).contains(&magnification) {
+        return Err(QRError::PrinterLimit(format!("^BQ magnification {} is not between 1 and {}", magnification, MAX_DOT_SIZE)));
+    }
+    let level = match err_correction_level {
+        ECLevel::Low => 'L',
+        ECLevel::Medium => 'M',
+        ECLevel::Q => 'Q',
+        ECLevel::High => 'H'
+    };
+
+    // `^FH` lets `_` followed by two hex digits stand for any byte, which
+    // covers the command characters and anything outside printable ASCII
+    let mut field = String::with_capacity(message.len());
+    for &byte in message.iter() {
+        match byte {
+            b'_' | b'^' | b'~' => field.push_str(&format!("_{:02X}", byte)),
+            0x20..=0x7E => field.push(byte as char),
+            _ => field.push_str(&format!("_{:02X}", byte))
+        }
+    }
+    // model 2, automatic mode selection after the level
+    Ok(format!("^XA\n^FO0,0^BQN,2,{}^FH^FD{}A,{}^FS\n^XZ\n", magnification, level, field))
+}
+
+// ESC/POS commands printing the symbol as a raster bit image (`GS v 0`),
+// centred, followed by a few lines of feed to clear the tear bar. Fails when
+// the symbol is wider than the `head_dots` the printer can print across.
+pub fn to_escpos(qr: &QR, dot_size: usize, quiet_zone: usize, head_dots: usize) -> Result<Vec<u8>, QRError> {
+    check_dot_size(dot_size)?;
+    let dots = (qr.width() + quiet_zone * 2) * dot_size;
+    if dots > head_dots {
+        return Err(QRError::PrinterLimit(format!("the symbol is {} dots across but the print head is {}", dots, head_dots)));
+    }
+    let (row_bytes, rows, bits) = bitmap(qr, dot_size, quiet_zone);
+    // `GS v 0` gives the width in bytes and the height in dots in two bytes each
+    if row_bytes > 0xFFFF || rows > 0xFFFF {
+        return Err(QRError::PrinterLimit(format!("a {} dot image is too large for GS v 0", rows)));
+    }
+
+    let mut out = Vec::with_capacity(bits.len() + 16);
+    // initialise the printer and centre what follows
+    out.extend_from_slice(&[ESC, b'@', ESC, b'a', 1]);
+    // normal density, then the width in bytes and the height in dots, low byte first
+    out.extend_from_slice(&[GS, b'v', b'0', 0]);
+    out.extend_from_slice(&[row_bytes as u8, (row_bytes >> 8) as u8, rows as u8, (rows >> 8) as u8]);
+    out.extend_from_slice(&bits);
+    // back to left alignment and feed three lines
+    out.extend_from_slice(&[ESC, b'a', 0, ESC, b'd', 3]);
+    Ok(out)
+}
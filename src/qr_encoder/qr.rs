@@ -1,18 +1,88 @@
+use alloc::vec::Vec;
+
 use qr_encoder::cell::{
     Cell,
     Point,
-    CellType,
     Color
 };
 
-use qr_encoder::config::{QRConfig};
-use qr_encoder::util::{set_color, get_index_value};
+use qr_encoder::config::{QRConfig, ECLevel};
 
+#[derive(Debug, Clone)]
 pub struct QR {
-    pub body: Vec<Cell>
+    pub body: Vec<Cell>,
+    // the level the symbol was actually encoded with, which can differ from
+    // the requested one when the level is maximised for a fixed version
+    pub err_correction_level: ECLevel,
+    // the body holds the symbol as it is printed: with light and dark swapped,
+    // the quiet zone included, when `inverted`, and flipped left to right
+    // when `mirrored`. `restore` undoes both.
+    pub inverted: bool,
+    pub mirrored: bool,
+    // modules along each side, worked out once since every module lookup needs
+    // it. The body always holds width * width cells.
+    width: usize
 }
 
 impl QR {
+    pub fn new(body: Vec<Cell>, err_correction_level: ECLevel) -> QR {
+        let mut width = 0;
+        while width * width < body.len() {
+            width += 1;
+        }
+        QR { body, err_correction_level, inverted: false, mirrored: false, width }
+    }
+
+    // swaps light and dark modules. Renderers draw the quiet zone dark for an
+    // inverted symbol.
+    pub fn invert(&mut self) {
+        for cell in self.body.iter_mut() {
+            cell.color = Color { r: 255 - cell.color.r, g: 255 - cell.color.g, b: 255 - cell.color.b };
+        }
+        self.inverted = !self.inverted;
+    }
+
+    // flips the symbol left to right, as it is seen through glass
+    pub fn mirror(&mut self) {
+        let width = self.width();
+        let mut body = Vec::with_capacity(self.body.len());
+        for row in 0..width {
+            for col in 0..width {
+                let mut cell = self.body[row * width + (width - 1 - col)].clone();
+                cell.point = Point(row, col);
+                body.push(cell);
+            }
+        }
+        self.body = body;
+        self.mirrored = !self.mirrored;
+    }
+
+    // the symbol as it was encoded, with any inversion and mirroring undone
+    pub fn restore(&self) -> QR {
+        let mut qr = self.clone();
+        if qr.mirrored {
+            qr.mirror();
+        }
+        if qr.inverted {
+            qr.invert();
+        }
+        qr
+    }
+
+    // number of modules along each side, not counting the quiet zone
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn is_dark(&self, row: usize, col: usize) -> bool {
+        self.body[row * self.width + col].is_black()
+    }
+
+    // the module matrix in row-major order, 1 for dark modules and 0 for light
+    pub fn modules(&self) -> Vec<u8> {
+        self.body.iter().map(|cell| if cell.is_black() { 1 } else { 0 }).collect()
+    }
+
     pub fn setup(&mut self, config: &QRConfig) {
         for alignment_point in config.finder_points.iter() {
             let point = Point(alignment_point.0, alignment_point.1);
@@ -34,8 +104,5 @@ impl QR {
         if config.version > 6 {
             config.apply_version_information_areas(&mut self.body);
         }
-
-        println!("LENGTH IS {}, SIZE IS {}, VERSION: {}", self.body.len(), config.size, config.version);
-        println!("--- QR ENCODER READY FOR ENCODING ---");
     }
 }
@@ -0,0 +1,322 @@
+use std::io;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use image_lib::{ImageBuffer, Rgba, RgbaImage, ImageRgba8, ColorType};
+use image_lib::png::PNGEncoder;
+use image_lib::imageops::{resize, FilterType};
+
+use qr_encoder::cell::{CellType, Color};
+use qr_encoder::debug::{Annotation, codeword_edges};
+use qr_encoder::error::QRError;
+use qr_encoder::logo::LogoArea;
+use qr_encoder::payment::{swiss_cross, SWISS_SYMBOL_MM};
+use qr_encoder::qr::QR;
+use qr_encoder::style::{Style, RoundedRect, layout};
+use qr_encoder::util::get_pixel_points;
+
+// 3x5 bitmaps for the digits 0-9, one row per 3 bits
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111]
+];
+
+const DEBUG_MODULE_SIZE: u32 = 32;
+const DEBUG_QUIET_ZONE: u32 = 4;
+
+pub fn create_qr_image(qr: &QR) -> RgbaImage {
+    let dimensions: u32 = qr.width() as u32;
+    let mut img = ImageBuffer::new(dimensions * 28, dimensions * 28);
+
+    // an inverted symbol has a dark quiet zone
+    let quiet_zone = if qr.inverted { 0 } else { 255 };
+    for pixel in img.pixels_mut() {
+        *pixel = Rgba { data: [quiet_zone, quiet_zone, quiet_zone, 255] };
+    }
+
+    for cell in &qr.body {
+        for pixel in get_pixel_points(cell) {
+            let (x, y, color) = pixel;
+            let rgb = Rgba { data: [color.r as u8, color.g as u8, color.b as u8, 255] };
+            img.put_pixel(x, y, rgb);
+        }
+    }
+
+    img
+}
+
+// writes the image in the format implied by the file extension
+pub fn save_qr_image(qr: &QR, path: &Path) -> io::Result<()> {
+    ImageRgba8(create_qr_image(qr)).save(path)
+}
+
+// renders the symbol `module_size` pixels a module with `quiet_zone` modules
+// of padding, rather than at the fixed size of `create_qr_image`
+pub fn create_scaled_image(qr: &QR, module_size: u32, quiet_zone: u32) -> RgbaImage {
+    let width = qr.width() as u32;
+    let dimensions = (width + quiet_zone * 2) * module_size;
+    ImageBuffer::from_fn(dimensions, dimensions, |px, py| {
+        let (col, row) = (px / module_size, py / module_size);
+        let inside = col >= quiet_zone && row >= quiet_zone && col < width + quiet_zone && row < width + quiet_zone;
+        // an inverted symbol has a dark quiet zone
+        let dark = if inside { qr.is_dark((row - quiet_zone) as usize, (col - quiet_zone) as usize) } else { qr.inverted };
+        let level = if dark { 0 } else { 255 };
+        Rgba { data: [level, level, level, 255] }
+    })
+}
+
+// the image as PNG file contents
+pub fn encode_png(img: &RgbaImage) -> io::Result<Vec<u8>> {
+    let mut png = vec![];
+    PNGEncoder::new(&mut png).encode(img, img.width(), img.height(), ColorType::RGBA(8))?;
+    Ok(png)
+}
+
+// samples taken along each side of a pixel to smooth the edges of shapes
+const STYLE_SAMPLES: u32 = 3;
+
+// paints the pixels covered by `covers`, a test in module units, within the
+// bounding box of `rect`, blending partly covered pixels with what is there
+fn paint_shape<F: Fn(f32, f32) -> bool>(img: &mut RgbaImage, rect: &RoundedRect, style: &Style, color: Option<Color>, covers: F) {
+    let size = style.module_size as f32;
+    let offset = style.quiet_zone as f32;
+    let span = (img.width() as f32 / size) - offset * 2.0;
+    let left = ((rect.x + offset) * size) as u32;
+    let top = ((rect.y + offset) * size) as u32;
+    let right = ((rect.x + rect.width + offset) * size).ceil() as u32;
+    let bottom = ((rect.y + rect.height + offset) * size).ceil() as u32;
+
+    for py in top..bottom.min(img.height()) {
+        for px in left..right.min(img.width()) {
+            let mut covered = 0;
+            for sy in 0..STYLE_SAMPLES {
+                for sx in 0..STYLE_SAMPLES {
+                    let x = (px as f32 + (sx as f32 + 0.5) / STYLE_SAMPLES as f32) / size - offset;
+                    let y = (py as f32 + (sy as f32 + 0.5) / STYLE_SAMPLES as f32) / size - offset;
+                    if covers(x, y) {
+                        covered += 1;
+                    }
+                }
+            }
+            if covered == 0 {
+                continue;
+            }
+
+            let fill = match color {
+                Some(color) => color,
+                None => style.fill.color_at((px as f32 / size - offset) / span, (py as f32 / size - offset) / span)
+            };
+            let alpha = covered as f32 / (STYLE_SAMPLES * STYLE_SAMPLES) as f32;
+            let current = img.get_pixel(px, py).data;
+            let blend = |under: u8, over: u32| (under as f32 * (1.0 - alpha) + over as f32 * alpha + 0.5) as u8;
+            img.put_pixel(px, py, Rgba { data: [blend(current[0], fill.r), blend(current[1], fill.g), blend(current[2], fill.b), 255] });
+        }
+    }
+}
+
+// renders the symbol with module shapes, eye shapes and a fill from `style`,
+// after checking the style keeps the symbol readable. An inverted symbol is
+// drawn in the background colour on the fill.
+pub fn create_styled_image(qr: &QR, style: &Style) -> Result<RgbaImage, QRError> {
+    style.check()?;
+
+    let symbol = layout(qr, style);
+    let dimensions = ((symbol.width + style.quiet_zone * 2) * style.module_size) as u32;
+    let mut img = if qr.inverted {
+        let size = style.module_size as f32;
+        let offset = style.quiet_zone as f32;
+        let span = symbol.width as f32;
+        ImageBuffer::from_fn(dimensions, dimensions, |px, py| {
+            rgba(style.fill.color_at((px as f32 / size - offset) / span, (py as f32 / size - offset) / span))
+        })
+    } else {
+        ImageBuffer::from_pixel(dimensions, dimensions, rgba(style.background))
+    };
+    let paint = |color: Option<Color>| if qr.inverted { Some(style.background) } else { color };
+
+    for rect in symbol.modules.iter() {
+        paint_shape(&mut img, rect, style, paint(None), |x, y| rect.contains(x, y));
+    }
+    for eye in symbol.eyes.iter() {
+        paint_shape(&mut img, &eye.outer, style, paint(style.eyes.outer_color), |x, y| eye.is_ring(x, y));
+        paint_shape(&mut img, &eye.ball, style, paint(style.eyes.inner_color), |x, y| eye.is_ball(x, y));
+    }
+
+    Ok(img)
+}
+
+pub fn save_styled_image(qr: &QR, style: &Style, path: &Path) -> io::Result<()> {
+    let img = create_styled_image(qr, style)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidInput, err.to_string()))?;
+    ImageRgba8(img).save(path)
+}
+
+// renders a Swiss QR-bill symbol at its printed size of 46mm for a printer
+// of `dpi` dots per inch, without a quiet zone, with the Swiss cross over its
+// middle. Each pixel takes the colour under its centre.
+pub fn create_swiss_image(qr: &QR, dpi: u32) -> RgbaImage {
+    let width = qr.width();
+    let pixels = (SWISS_SYMBOL_MM / 25.4 * dpi as f32).round() as u32;
+    let scale = width as f32 / pixels as f32;
+    let cross = swiss_cross(width);
+
+    ImageBuffer::from_fn(pixels, pixels, |px, py| {
+        let (x, y) = ((px as f32 + 0.5) * scale, (py as f32 + 0.5) * scale);
+        let mut color = if qr.is_dark(y as usize, x as usize) { Color { r: 0, g: 0, b: 0 } } else { Color { r: 255, g: 255, b: 255 } };
+        for &(ref rect, fill) in cross.iter() {
+            if rect.contains(x, y) {
+                color = fill;
+            }
+        }
+        rgba(color)
+    })
+}
+
+pub fn save_swiss_image(qr: &QR, dpi: u32, path: &Path) -> io::Result<()> {
+    ImageRgba8(create_swiss_image(qr, dpi)).save(path)
+}
+
+// loads a logo to go over the symbol
+pub fn open_logo(path: &Path) -> io::Result<RgbaImage> {
+    image_lib::open(path)
+        .map(|img| img.to_rgba())
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err.to_string()))
+}
+
+// composites `logo` into `area` of an image rendered with `style`, scaled to
+// fit and centred. The quiet box around it is cleared to the background, and
+// function modules under the area are drawn back on top so alignment patterns
+// stay intact.
+pub fn overlay_logo(img: &mut RgbaImage, qr: &QR, area: &LogoArea, logo: &RgbaImage, style: &Style) {
+    let size = style.module_size as u32;
+    let offset = style.quiet_zone as u32;
+    let width = qr.width();
+
+    let left = (area.col as u32 + offset) * size;
+    let top = (area.row as u32 + offset) * size;
+    fill(img, left, top, area.size as u32 * size, area.size as u32 * size, rgba(style.background));
+
+    let room = area.logo_size() as u32 * size;
+    if room > 0 && logo.width() > 0 && logo.height() > 0 {
+        let (logo_width, logo_height) = if logo.width() >= logo.height() {
+            (room, (room * logo.height() / logo.width()).max(1))
+        } else {
+            ((room * logo.width() / logo.height()).max(1), room)
+        };
+        let scaled = resize(logo, logo_width, logo_height, FilterType::Lanczos3);
+        let x = left + area.margin as u32 * size + (room - logo_width) / 2;
+        let y = top + area.margin as u32 * size + (room - logo_height) / 2;
+
+        for (px, py, pixel) in scaled.enumerate_pixels() {
+            let [r, g, b, a] = pixel.data;
+            let alpha = a as u32;
+            let under = img.get_pixel(x + px, y + py).data;
+            let blend = |over: u8, under: u8| ((over as u32 * alpha + under as u32 * (255 - alpha)) / 255) as u8;
+            img.put_pixel(x + px, y + py, Rgba { data: [blend(r, under[0]), blend(g, under[1]), blend(b, under[2]), 255] });
+        }
+    }
+
+    for row in area.row..(area.row + area.size) {
+        for col in area.col..(area.col + area.size) {
+            let cell = &qr.body[row * width + col];
+            match cell.module_type {
+                CellType::Message | CellType::None => continue,
+                _ => {}
+            }
+            let color = if cell.is_black() {
+                style.fill.color_at(col as f32 / width as f32, row as f32 / width as f32)
+            } else {
+                style.background
+            };
+            fill(img, (col as u32 + offset) * size, (row as u32 + offset) * size, size, size, rgba(color));
+        }
+    }
+}
+
+fn rgba(color: Color) -> Rgba<u8> {
+    Rgba { data: [color.r as u8, color.g as u8, color.b as u8, 255] }
+}
+
+fn fill(img: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+    for px in x..(x + width) {
+        for py in y..(y + height) {
+            img.put_pixel(px, py, color);
+        }
+    }
+}
+
+fn draw_number(img: &mut RgbaImage, x: u32, y: u32, number: usize, scale: u32) {
+    let black = Rgba { data: [0, 0, 0, 255] };
+    let digits = format!("{}", number);
+
+    for (position, digit) in digits.bytes().enumerate() {
+        let glyph = DIGITS[(digit - b'0') as usize];
+        let left = x + position as u32 * 4 * scale;
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..3 {
+                if (bits >> (2 - col)) & 1 == 1 {
+                    fill(img, left + col * scale, y + row as u32 * scale, scale, scale, black);
+                }
+            }
+        }
+    }
+}
+
+// shades every module by its role, outlines each codeword's modules and
+// labels each codeword with its index in the interleaved stream
+pub fn create_debug_image(qr: &QR, annotations: &[Annotation]) -> RgbaImage {
+    let width = qr.width();
+    let size = DEBUG_MODULE_SIZE;
+    let dimensions = (width as u32 + DEBUG_QUIET_ZONE * 2) * size;
+    let mut img = ImageBuffer::from_pixel(dimensions, dimensions, Rgba { data: [255, 255, 255, 255] });
+    let black = Rgba { data: [0, 0, 0, 255] };
+
+    for row in 0..width {
+        for col in 0..width {
+            let annotation = &annotations[row * width + col];
+            let x = (col as u32 + DEBUG_QUIET_ZONE) * size;
+            let y = (row as u32 + DEBUG_QUIET_ZONE) * size;
+            fill(&mut img, x, y, size, size, rgba(annotation.color()));
+
+            let edges = codeword_edges(annotations, width, row, col);
+            if edges[0] {
+                fill(&mut img, x, y, size, 1, black);
+            }
+            if edges[1] {
+                fill(&mut img, x + size - 1, y, 1, size, black);
+            }
+            if edges[2] {
+                fill(&mut img, x, y + size - 1, size, 1, black);
+            }
+            if edges[3] {
+                fill(&mut img, x, y, 1, size, black);
+            }
+        }
+    }
+
+    // labels go on last so neighbouring modules don't paint over them
+    for row in 0..width {
+        for col in 0..width {
+            let role = &annotations[row * width + col].role;
+            if role.is_first_bit() {
+                let x = (col as u32 + DEBUG_QUIET_ZONE) * size;
+                let y = (row as u32 + DEBUG_QUIET_ZONE) * size;
+                draw_number(&mut img, x + 2, y + 2, role.codeword().unwrap(), 2);
+            }
+        }
+    }
+
+    img
+}
+
+pub fn save_debug_image(qr: &QR, annotations: &[Annotation], path: &Path) -> io::Result<()> {
+    ImageRgba8(create_debug_image(qr, annotations)).save(path)
+}
@@ -0,0 +1,377 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use qr_encoder::bits::{BitBuffer, BitReader};
+use qr_encoder::config::EncodingMode;
+use qr_encoder::error::QRError;
+use qr_encoder::util::get_content_length;
+
+pub const ALPHANUMERIC_CHARSET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+const ECI_INDICATOR: u32 = 7;
+
+// a run of the message encoded in a single mode. The data is expected to be
+// valid for the mode; the constructors below check it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    // the digits 0-9 as ASCII
+    Numeric(Vec<u8>),
+    // characters from the 45 character alphanumeric set
+    AlphaNumeric(Vec<u8>),
+    Byte(Vec<u8>),
+    // double byte Shift JIS characters
+    Kanji(Vec<u8>),
+    // Extended Channel Interpretation designator, for the segments that follow it
+    Eci(u32)
+}
+
+pub fn alphanumeric_value(c: u8) -> Option<u32> {
+    ALPHANUMERIC_CHARSET.iter().position(|&a| a == c).map(|v| v as u32)
+}
+
+// the 13 bit value of a Shift JIS double byte character in the Kanji range
+pub fn kanji_value(high: u8, low: u8) -> Option<u32> {
+    let code = ((high as u32) << 8) | low as u32;
+    let offset = match code {
+        0x8140..=0x9FFC => code - 0x8140,
+        0xE040..=0xEBBF => code - 0xC140,
+        _ => return None
+    };
+
+    Some((offset >> 8) * 0xC0 + (offset & 0xFF))
+}
+
+// the Shift JIS bytes for a 13 bit Kanji value
+fn kanji_bytes(value: u32) -> [u8; 2] {
+    let offset = ((value / 0xC0) << 8) | (value % 0xC0);
+    let code = if offset + 0x8140 <= 0x9FFC { offset + 0x8140 } else { offset + 0xC140 };
+    [(code >> 8) as u8, code as u8]
+}
+
+fn invalid(mode: EncodingMode, data: &[u8], valid: &dyn Fn(u8) -> bool) -> Result<(), QRError> {
+    match data.iter().find(|&&b| !valid(b)) {
+        Some(&byte) => Err(QRError::InvalidCharacter { mode, byte }),
+        None => Ok(())
+    }
+}
+
+impl Segment {
+    pub fn numeric(data: &[u8]) -> Result<Segment, QRError> {
+        invalid(EncodingMode::Numeric, data, &|b| b.is_ascii_digit())?;
+        Ok(Segment::Numeric(data.to_vec()))
+    }
+
+    pub fn alphanumeric(data: &[u8]) -> Result<Segment, QRError> {
+        invalid(EncodingMode::AlphaNumeric, data, &|b| alphanumeric_value(b).is_some())?;
+        Ok(Segment::AlphaNumeric(data.to_vec()))
+    }
+
+    pub fn byte(data: &[u8]) -> Segment {
+        Segment::Byte(data.to_vec())
+    }
+
+    pub fn kanji(data: &[u8]) -> Result<Segment, QRError> {
+        if data.len() % 2 == 1 {
+            return Err(QRError::InvalidCharacter { mode: EncodingMode::Japanese, byte: data[data.len() - 1] });
+        }
+        for pair in data.chunks(2) {
+            if kanji_value(pair[0], pair[1]).is_none() {
+                return Err(QRError::InvalidCharacter { mode: EncodingMode::Japanese, byte: pair[0] });
+            }
+        }
+        Ok(Segment::Kanji(data.to_vec()))
+    }
+
+    pub fn eci(designator: u32) -> Result<Segment, QRError> {
+        if designator >= 1_000_000 {
+            return Err(QRError::InvalidEci(designator));
+        }
+        Ok(Segment::Eci(designator))
+    }
+
+    // the data in the mode that takes the fewest bits: numeric for digits,
+    // alphanumeric for upper case text from its 45 characters, otherwise bytes
+    pub fn cheapest(data: &[u8]) -> Segment {
+        if data.iter().all(|b| b.is_ascii_digit()) {
+            Segment::Numeric(data.to_vec())
+        } else if data.iter().all(|&b| alphanumeric_value(b).is_some()) {
+            Segment::AlphaNumeric(data.to_vec())
+        } else {
+            Segment::Byte(data.to_vec())
+        }
+    }
+
+    // a segment in the given mode, checking the data is valid for it
+    pub fn with_mode(mode: EncodingMode, data: &[u8]) -> Result<Segment, QRError> {
+        match mode {
+            EncodingMode::Numeric => Segment::numeric(data),
+            EncodingMode::AlphaNumeric => Segment::alphanumeric(data),
+            EncodingMode::Byte => Ok(Segment::byte(data)),
+            EncodingMode::Japanese => Segment::kanji(data)
+        }
+    }
+
+    pub fn mode(&self) -> Option<EncodingMode> {
+        match *self {
+            Segment::Numeric(_) => Some(EncodingMode::Numeric),
+            Segment::AlphaNumeric(_) => Some(EncodingMode::AlphaNumeric),
+            Segment::Byte(_) => Some(EncodingMode::Byte),
+            Segment::Kanji(_) => Some(EncodingMode::Japanese),
+            Segment::Eci(_) => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Segment::Numeric(_) => "Numeric",
+            Segment::AlphaNumeric(_) => "AlphaNumeric",
+            Segment::Byte(_) => "Byte",
+            Segment::Kanji(_) => "Japanese",
+            Segment::Eci(_) => "Eci"
+        }
+    }
+
+    pub fn char_count(&self) -> usize {
+        match *self {
+            Segment::Numeric(ref data) | Segment::AlphaNumeric(ref data) | Segment::Byte(ref data) => data.len(),
+            Segment::Kanji(ref data) => data.len() / 2,
+            Segment::Eci(_) => 0
+        }
+    }
+
+    // number of bits the segment takes up, header included
+    pub fn bit_length(&self, version: usize) -> usize {
+        let mut buffer = BitBuffer::new();
+        self.write(&mut buffer, version);
+        buffer.len()
+    }
+
+    // writes the mode indicator, character count indicator and data
+    pub fn write(&self, buffer: &mut BitBuffer, version: usize) {
+        let mode = match *self {
+            Segment::Eci(designator) => {
+                buffer.append(ECI_INDICATOR, 4);
+                if designator < (1 << 7) {
+                    buffer.append(designator, 8);
+                } else if designator < (1 << 14) {
+                    buffer.append(0b10, 2);
+                    buffer.append(designator, 14);
+                } else {
+                    buffer.append(0b110, 3);
+                    buffer.append(designator, 21);
+                }
+                return;
+            },
+            _ => self.mode().unwrap()
+        };
+
+        buffer.append(mode.indicator() as u32, 4);
+        buffer.append(self.char_count() as u32, get_content_length(mode.indicator(), version));
+
+        match *self {
+            Segment::Numeric(ref data) => {
+                // groups of three digits in 10 bits, with 4 or 7 bits for a shorter last group
+                for group in data.chunks(3) {
+                    let value = group.iter().fold(0, |acc, d| acc * 10 + (d - b'0') as u32);
+                    buffer.append(value, group.len() * 3 + 1);
+                }
+            },
+            Segment::AlphaNumeric(ref data) => {
+                // pairs of characters in 11 bits, with 6 bits for a trailing character
+                for pair in data.chunks(2) {
+                    let first = alphanumeric_value(pair[0]).unwrap_or(0);
+                    if pair.len() == 2 {
+                        buffer.append(first * 45 + alphanumeric_value(pair[1]).unwrap_or(0), 11);
+                    } else {
+                        buffer.append(first, 6);
+                    }
+                }
+            },
+            Segment::Byte(ref data) => {
+                for byte in data {
+                    buffer.append(*byte as u32, 8);
+                }
+            },
+            Segment::Kanji(ref data) => {
+                for pair in data.chunks(2) {
+                    buffer.append(kanji_value(pair[0], pair[1]).unwrap_or(0), 13);
+                }
+            },
+            Segment::Eci(_) => {}
+        }
+    }
+}
+
+// the cost of a character in each of numeric, alphanumeric and byte mode, in
+// sixths of a bit so numeric's 10 bits for 3 digits and alphanumeric's 11
+// bits for 2 characters come out whole
+const CHARACTER_COST: [usize; 3] = [20, 33, 48];
+const SPLIT_MODES: [EncodingMode; 3] = [EncodingMode::Numeric, EncodingMode::AlphaNumeric, EncodingMode::Byte];
+
+fn segment_for(mode: EncodingMode, data: &[u8]) -> Segment {
+    match mode {
+        EncodingMode::Numeric => Segment::Numeric(data.to_vec()),
+        EncodingMode::AlphaNumeric => Segment::AlphaNumeric(data.to_vec()),
+        _ => Segment::Byte(data.to_vec())
+    }
+}
+
+// splits the data into numeric, alphanumeric and byte segments so it takes
+// the fewest bits at a version, weighing what each character costs in a mode
+// against the header each change of mode adds
+pub fn optimal_segments(data: &[u8], version: usize) -> Vec<Segment> {
+    if data.is_empty() {
+        return vec![];
+    }
+
+    let encodable = |mode: usize, byte: u8| match mode {
+        0 => byte.is_ascii_digit(),
+        1 => alphanumeric_value(byte).is_some(),
+        _ => true
+    };
+    let header = |mode: usize| (4 + get_content_length(SPLIT_MODES[mode].indicator(), version)) * 6;
+
+    // the cheapest way to reach each character in each mode, and the mode of
+    // the character before it on that path
+    let unreachable = usize::MAX;
+    let mut costs = [unreachable; 3];
+    for (mode, cost) in costs.iter_mut().enumerate() {
+        if encodable(mode, data[0]) {
+            *cost = header(mode) + CHARACTER_COST[mode];
+        }
+    }
+    let mut previous: Vec<[usize; 3]> = vec![[0; 3]];
+    for &byte in data[1..].iter() {
+        let mut next = [unreachable; 3];
+        let mut from = [0; 3];
+        for mode in 0..3 {
+            if !encodable(mode, byte) {
+                continue;
+            }
+            for (before, &cost) in costs.iter().enumerate() {
+                if cost == unreachable {
+                    continue;
+                }
+                let switch = if before == mode { 0 } else { header(mode) };
+                let total = cost + switch + CHARACTER_COST[mode];
+                if total < next[mode] {
+                    next[mode] = total;
+                    from[mode] = before;
+                }
+            }
+        }
+        costs = next;
+        previous.push(from);
+    }
+
+    // walk back along the cheapest path to get each character's mode
+    let mut mode = (0..3).min_by_key(|mode| costs[*mode]).unwrap();
+    let mut modes = vec![0; data.len()];
+    for i in (0..data.len()).rev() {
+        modes[i] = mode;
+        mode = previous[i][mode];
+    }
+
+    // group runs of a mode into segments, keeping within what the character
+    // count indicator can hold
+    let mut segments = vec![];
+    let mut start = 0;
+    for end in 1..=data.len() {
+        let mode = modes[start];
+        let limit = (1 << get_content_length(SPLIT_MODES[mode].indicator(), version)) - 1;
+        if end == data.len() || modes[end] != mode || end - start == limit {
+            segments.push(segment_for(SPLIT_MODES[mode], &data[start..end]));
+            start = end;
+        }
+    }
+    segments
+}
+
+// number of bits a list of segments takes up at a version
+pub fn segments_bit_length(segments: &[Segment], version: usize) -> usize {
+    segments.iter().map(|segment| segment.bit_length(version)).sum()
+}
+
+fn truncated() -> QRError {
+    QRError::Undecodable(String::from("the data ends part way through a segment"))
+}
+
+// reads segments back out of data codewords, stopping at the terminator
+pub fn read_segments(data: &[u8], version: usize) -> Result<Vec<Segment>, QRError> {
+    let mut reader = BitReader::new(data);
+    let mut segments = vec![];
+
+    while reader.remaining() >= 4 {
+        let indicator = reader.read(4).unwrap();
+        let mode = match indicator {
+            0 => break,
+            1 => EncodingMode::Numeric,
+            2 => EncodingMode::AlphaNumeric,
+            4 => EncodingMode::Byte,
+            8 => EncodingMode::Japanese,
+            7 => {
+                let designator = match reader.read(1).ok_or_else(truncated)? {
+                    0 => reader.read(7),
+                    _ => match reader.read(1).ok_or_else(truncated)? {
+                        0 => reader.read(14),
+                        _ => reader.read(1).and_then(|_| reader.read(21))
+                    }
+                };
+                segments.push(Segment::Eci(designator.ok_or_else(truncated)?));
+                continue;
+            },
+            _ => return Err(QRError::Undecodable(format!("unknown mode indicator {:04b}", indicator)))
+        };
+
+        let count = reader.read(get_content_length(mode.indicator(), version)).ok_or_else(truncated)? as usize;
+        let mut bytes: Vec<u8> = vec![];
+        match mode {
+            EncodingMode::Numeric => {
+                let mut left = count;
+                while left > 0 {
+                    let digits = if left >= 3 { 3 } else { left };
+                    let value = reader.read(digits * 3 + 1).ok_or_else(truncated)?;
+                    let text = format!("{:0width$}", value, width = digits);
+                    if text.len() != digits {
+                        return Err(QRError::Undecodable(String::from("a numeric group is out of range")));
+                    }
+                    bytes.extend_from_slice(text.as_bytes());
+                    left -= digits;
+                }
+            },
+            EncodingMode::AlphaNumeric => {
+                let mut left = count;
+                while left > 0 {
+                    let (pair, bits) = if left >= 2 { (true, 11) } else { (false, 6) };
+                    let value = reader.read(bits).ok_or_else(truncated)? as usize;
+                    let chars = if pair { [value / 45, value % 45] } else { [value, 0] };
+                    for c in chars.iter().take(if pair { 2 } else { 1 }) {
+                        match ALPHANUMERIC_CHARSET.get(*c) {
+                            Some(byte) => bytes.push(*byte),
+                            None => return Err(QRError::Undecodable(String::from("an alphanumeric pair is out of range")))
+                        }
+                    }
+                    left -= if pair { 2 } else { 1 };
+                }
+            },
+            EncodingMode::Byte => {
+                for _ in 0..count {
+                    bytes.push(reader.read(8).ok_or_else(truncated)? as u8);
+                }
+            },
+            EncodingMode::Japanese => {
+                for _ in 0..count {
+                    bytes.extend_from_slice(&kanji_bytes(reader.read(13).ok_or_else(truncated)?));
+                }
+            }
+        }
+
+        segments.push(match mode {
+            EncodingMode::Numeric => Segment::Numeric(bytes),
+            EncodingMode::AlphaNumeric => Segment::AlphaNumeric(bytes),
+            EncodingMode::Byte => Segment::Byte(bytes),
+            EncodingMode::Japanese => Segment::Kanji(bytes)
+        });
+    }
+
+    Ok(segments)
+}
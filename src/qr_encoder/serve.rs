@@ -0,0 +1,544 @@
+use std::io;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use qr_encoder::config::{QRConfig, ECLevel};
+use qr_encoder::error::QRError;
+use qr_encoder::raster::{create_scaled_image, encode_png};
+use qr_encoder::svg::to_svg;
+use qr_encoder::text::to_text;
+use qr_encoder::trace::json_string;
+
+// the most a request may send: the request line and headers, and a JSON body
+const MAX_HEAD_BYTES: usize = 8 * 1024;
+const MAX_BODY_BYTES: usize = 16 * 1024;
+// what a version 40 symbol holds in byte mode at level L
+const MAX_DATA_BYTES: usize = 2953;
+const MAX_SCALE: usize = 32;
+const DEFAULT_SCALE: usize = 8;
+const QUIET_ZONE: usize = 4;
+// how long a client has to send its whole request, so one that trickles it
+// in a byte at a time is dropped rather than holding a worker
+const TIMEOUT: Duration = Duration::from_secs(5);
+// the connections answered at once; more wait in the listen backlog
+const MAX_CONNECTIONS: usize = 32;
+// the pause after a failed accept, so running out of file descriptors
+// doesn't turn into a busy loop
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    Svg,
+    Png,
+    Txt
+}
+
+impl Format {
+    fn name(&self) -> &'static str {
+        match *self {
+            Format::Svg => "svg",
+            Format::Png => "png",
+            Format::Txt => "txt"
+        }
+    }
+
+    fn content_type(&self) -> &'static str {
+        match *self {
+            Format::Svg => "image/svg+xml",
+            Format::Png => "image/png",
+            Format::Txt => "text/plain; charset=utf-8"
+        }
+    }
+}
+
+// an HTTP request, with the path split from its query
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>
+}
+
+impl Request {
+    // the first header called `name`, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|&(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>
+}
+
+impl Response {
+    fn new(status: u16, content_type: &str, body: Vec<u8>) -> Response {
+        Response { status, headers: vec![("Content-Type", String::from(content_type))], body }
+    }
+
+    // errors are sent as `{"error": "..."}`
+    pub fn error(status: u16, message: &str) -> Response {
+        let body = format!("{{\"error\":{}}}\n", json_string(message));
+        Response::new(status, "application/json", body.into_bytes())
+    }
+
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason(self.status));
+        for &(name, ref value) in self.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", self.body.len()));
+        out.write_all(head.as_bytes())?;
+        out.write_all(&self.body)?;
+        out.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Entity",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error"
+    }
+}
+
+// the status for an encoder error: data that doesn't fit is a well formed
+// request that can't be met, everything else a bad parameter
+pub fn error_status(err: &QRError) -> u16 {
+    match *err {
+        QRError::DataTooLong { .. } | QRError::SegmentsTooLong { .. } => 422,
+        QRError::Undecodable(_) => 500,
+        _ => 400
+    }
+}
+
+// what to render, from a query string or a JSON body
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderRequest {
+    pub data: Vec<u8>,
+    pub err_correction_level: ECLevel,
+    pub format: Format,
+    // pixels a module for SVG and PNG; text is always one character a module
+    pub scale: usize
+}
+
+impl RenderRequest {
+    fn new() -> RenderRequest {
+        RenderRequest { data: vec![], err_correction_level: ECLevel::Medium, format: Format::Svg, scale: DEFAULT_SCALE }
+    }
+
+    fn set(&mut self, key: &str, value: &[u8]) -> Result<(), Response> {
+        let text = || String::from_utf8_lossy(value).into_owned();
+        match key {
+            "data" => self.data = value.to_vec(),
+            "ec" => {
+                self.err_correction_level = text().parse::<ECLevel>()
+                    .map_err(|err| Response::error(400, &err.to_string()))?;
+            },
+            "format" => {
+                self.format = match text().as_str() {
+                    "svg" => Format::Svg,
+                    "png" => Format::Png,
+                    "txt" => Format::Txt,
+                    other => return Err(Response::error(400, &format!("format {:?} is not svg, png or txt", other)))
+                };
+            },
+            "scale" => {
+                self.scale = match text().parse::<usize>() {
+                    Ok(scale) if (1..=MAX_SCALE).contains(&scale) => scale,
+                    _ => return Err(Response::error(400, &format!("scale {:?} is not a number from 1 to {}", text(), MAX_SCALE)))
+                };
+            },
+            _ => return Err(Response::error(400, &format!("unknown parameter {:?}", key)))
+        }
+        Ok(())
+    }
+
+    fn check(self) -> Result<RenderRequest, Response> {
+        if self.data.is_empty() {
+            return Err(Response::error(400, "data is required"));
+        }
+        if self.data.len() > MAX_DATA_BYTES {
+            return Err(Response::error(413, &format!("data is {} bytes but at most {} are accepted", self.data.len(), MAX_DATA_BYTES)));
+        }
+        Ok(self)
+    }
+
+    // `data=...&ec=...&format=...&scale=...`, percent-encoded
+    pub fn from_query(query: &str) -> Result<RenderRequest, Response> {
+        let mut request = RenderRequest::new();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let mut parts = pair.splitn(2, '=');
+            let key = percent_decode(parts.next().unwrap_or(""))?;
+            let value = percent_decode(parts.next().unwrap_or(""))?;
+            request.set(&String::from_utf8_lossy(&key), &value)?;
+        }
+        request.check()
+    }
+
+    // `{"data": "...", "ec": "...", "format": "...", "scale": 8}`
+    pub fn from_json(body: &[u8]) -> Result<RenderRequest, Response> {
+        let body = String::from_utf8(body.to_vec())
+            .map_err(|_| Response::error(400, "the body is not valid UTF-8"))?;
+        let mut request = RenderRequest::new();
+        for (key, value) in parse_json_object(&body).map_err(|err| Response::error(400, &err))? {
+            request.set(&key, value.as_bytes())?;
+        }
+        request.check()
+    }
+
+    // a strong validator for the rendering: the same parameters on the same
+    // version of the encoder always give the same bytes
+    pub fn etag(&self) -> String {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let key = format!("{}/{:?}/{}/{}/", env!("CARGO_PKG_VERSION"), self.err_correction_level, self.format.name(), self.scale);
+        for byte in key.as_bytes().iter().chain(self.data.iter()) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("\"{:016x}\"", hash)
+    }
+
+    pub fn render(&self) -> Result<Vec<u8>, Response> {
+        let mut config = QRConfig::from_data(self.data.clone(), None, self.err_correction_level)
+            .map_err(|err| Response::error(error_status(&err), &err.to_string()))?;
+        let (qr, _) = config.gen_qr_code();
+        Ok(match self.format {
+            Format::Svg => to_svg(&qr, self.scale, QUIET_ZONE).into_bytes(),
+            Format::Txt => to_text(&qr, QUIET_ZONE).into_bytes(),
+            Format::Png => {
+                let img = create_scaled_image(&qr, self.scale as u32, QUIET_ZONE as u32);
+                encode_png(&img).map_err(|err| Response::error(500, &err.to_string()))?
+            }
+        })
+    }
+}
+
+fn percent_decode(value: &str) -> Result<Vec<u8>, Response> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = bytes.get(i + 1..i + 3)
+                    .and_then(|hex| String::from_utf8(hex.to_vec()).ok())
+                    .and_then(|hex| u8::from_str_radix(&hex, 16).ok());
+                match hex {
+                    Some(byte) => decoded.push(byte),
+                    None => return Err(Response::error(400, "the query has a malformed percent escape"))
+                }
+                i += 2;
+            },
+            byte => decoded.push(byte)
+        }
+        i += 1;
+    }
+    Ok(decoded)
+}
+
+// reads a flat JSON object whose values are strings or numbers, which is
+// all a render request needs
+fn parse_json_object(json: &str) -> Result<Vec<(String, String)>, String> {
+    let chars: Vec<char> = json.chars().collect();
+    let mut position = 0;
+    let skip_space = |position: &mut usize| {
+        while *position < chars.len() && chars[*position].is_whitespace() {
+            *position += 1;
+        }
+    };
+    let expect = |position: &mut usize, c: char| -> Result<(), String> {
+        skip_space(position);
+        if chars.get(*position) != Some(&c) {
+            return Err(format!("expected {:?} at character {}", c, *position));
+        }
+        *position += 1;
+        Ok(())
+    };
+
+    let mut fields = vec![];
+    expect(&mut position, '{')?;
+    skip_space(&mut position);
+    if chars.get(position) == Some(&'}') {
+        position += 1;
+    } else {
+        loop {
+            skip_space(&mut position);
+            let key = json_string_value(&chars, &mut position)?;
+            expect(&mut position, ':')?;
+            skip_space(&mut position);
+            let value = match chars.get(position) {
+                Some(&'"') => json_string_value(&chars, &mut position)?,
+                Some(c) if c.is_ascii_digit() || *c == '-' => {
+                    let start = position;
+                    while position < chars.len() && (chars[position].is_ascii_digit() || "-+.eE".contains(chars[position])) {
+                        position += 1;
+                    }
+                    chars[start..position].iter().collect()
+                },
+                _ => return Err(format!("the value of {:?} must be a string or a number", key))
+            };
+            fields.push((key, value));
+
+            skip_space(&mut position);
+            match chars.get(position) {
+                Some(&',') => position += 1,
+                Some(&'}') => {
+                    position += 1;
+                    break;
+                },
+                _ => return Err(format!("expected ',' or '}}' at character {}", position))
+            }
+        }
+    }
+    skip_space(&mut position);
+    if position != chars.len() {
+        return Err(String::from("unexpected data after the JSON object"));
+    }
+    Ok(fields)
+}
+
+fn json_string_value(chars: &[char], position: &mut usize) -> Result<String, String> {
+    if chars.get(*position) != Some(&'"') {
+        return Err(format!("expected a string at character {}", *position));
+    }
+    *position += 1;
+    let mut value = String::new();
+    loop {
+        let c = match chars.get(*position) {
+            Some(c) => *c,
+            None => return Err(String::from("a string is missing its closing quote"))
+        };
+        *position += 1;
+        match c {
+            '"' => return Ok(value),
+            '\\' => {
+                let escape = chars.get(*position).cloned().unwrap_or(' ');
+                *position += 1;
+                value.push(match escape {
+                    '"' | '\\' | '/' => escape,
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    'u' => {
+                        let mut code = json_hex(chars, position)?;
+                        // characters outside the basic plane come as a surrogate pair
+                        if (0xD800..0xDC00).contains(&code) && chars.get(*position..*position + 2) == Some(&['\\', 'u'][..]) {
+                            *position += 2;
+                            let low = json_hex(chars, position)?;
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                        }
+                        match ::std::char::from_u32(code) {
+                            Some(c) => c,
+                            None => return Err(format!("\\u{:04x} is not a character", code))
+                        }
+                    },
+                    _ => return Err(format!("unknown escape \\{}", escape))
+                });
+            },
+            c if (c as u32) < 0x20 => return Err(String::from("control characters must be escaped in strings")),
+            c => value.push(c)
+        }
+    }
+}
+
+fn json_hex(chars: &[char], position: &mut usize) -> Result<u32, String> {
+    let hex: String = chars.get(*position..*position + 4).map(|hex| hex.iter().collect()).unwrap_or_default();
+    *position += 4;
+    u32::from_str_radix(&hex, 16).map_err(|_| format!("{:?} is not a \\u escape", hex))
+}
+
+// reads a request, refusing heads and bodies over the limits
+pub fn read_request<R: Read>(stream: &mut R) -> Result<Request, Response> {
+    let mut buffer = vec![];
+    let mut chunk = [0; 1024];
+    let head_end = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            return Err(Response::error(431, "the request head is too large"));
+        }
+        match stream.read(&mut chunk) {
+            Ok(0) => return Err(Response::error(400, "the connection closed mid request")),
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+            Err(_) => return Err(Response::error(408, "the request took too long to arrive"))
+        }
+    };
+    if head_end > MAX_HEAD_BYTES {
+        return Err(Response::error(431, "the request head is too large"));
+    }
+
+    let head = String::from_utf8(buffer[..head_end].to_vec())
+        .map_err(|_| Response::error(400, "the request head is not valid UTF-8"))?;
+    let mut lines = head.split("\r\n");
+    let request_line: Vec<&str> = lines.next().unwrap_or("").split(' ').collect();
+    if request_line.len() != 3 || !request_line[2].starts_with("HTTP/1.") {
+        return Err(Response::error(400, "malformed request line"));
+    }
+    let mut target = request_line[1].splitn(2, '?');
+    let path = String::from(target.next().unwrap_or(""));
+    let query = String::from(target.next().unwrap_or(""));
+
+    let mut headers = vec![];
+    for line in lines {
+        let mut parts = line.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) => headers.push((String::from(name.trim()), String::from(value.trim()))),
+            _ => return Err(Response::error(400, "malformed header"))
+        }
+    }
+    let mut request = Request { method: String::from(request_line[0]), path, query, headers, body: vec![] };
+
+    if request.method == "POST" {
+        let length = match request.header("Content-Length").map(|length| length.parse::<usize>()) {
+            Some(Ok(length)) => length,
+            Some(Err(_)) => return Err(Response::error(400, "Content-Length is not a number")),
+            None => return Err(Response::error(411, "POST requests need a Content-Length"))
+        };
+        if length > MAX_BODY_BYTES {
+            return Err(Response::error(413, &format!("the body is {} bytes but at most {} are accepted", length, MAX_BODY_BYTES)));
+        }
+        let mut body = buffer[head_end + 4..].to_vec();
+        while body.len() < length {
+            match stream.read(&mut chunk) {
+                Ok(0) => return Err(Response::error(400, "the connection closed mid body")),
+                Ok(read) => body.extend_from_slice(&chunk[..read]),
+                Err(_) => return Err(Response::error(408, "the request took too long to arrive"))
+            }
+        }
+        body.truncate(length);
+        request.body = body;
+    }
+    Ok(request)
+}
+
+// answers a request: `GET /qr?...` or `POST /qr` with a JSON body
+pub fn handle(request: &Request) -> Response {
+    if request.path != "/qr" {
+        return Response::error(404, "the only endpoint is /qr");
+    }
+    let render = match request.method.as_str() {
+        "GET" => RenderRequest::from_query(&request.query),
+        "POST" => {
+            let json = request.header("Content-Type")
+                .is_some_and(|content_type| content_type.split(';').next().unwrap_or("").trim() == "application/json");
+            if !json {
+                return Response::error(415, "POST bodies must be application/json");
+            }
+            RenderRequest::from_json(&request.body)
+        },
+        _ => {
+            let mut response = Response::error(405, "only GET and POST are supported");
+            response.headers.push(("Allow", String::from("GET, POST")));
+            return response;
+        }
+    };
+    let render = match render {
+        Ok(render) => render,
+        Err(response) => return response
+    };
+
+    let etag = render.etag();
+    let cached = request.header("If-None-Match")
+        .is_some_and(|tags| tags.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"));
+    let mut response = if cached {
+        Response { status: 304, headers: vec![], body: vec![] }
+    } else {
+        match render.render() {
+            Ok(body) => Response::new(200, render.format.content_type(), body),
+            Err(response) => return response
+        }
+    };
+    response.headers.push(("ETag", etag));
+    response.headers.push(("Cache-Control", String::from("public, max-age=86400")));
+    response
+}
+
+// a stream whose reads all have to finish by one deadline
+struct DeadlineStream<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant
+}
+
+impl<'a> Read for DeadlineStream<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::from_secs(0) {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "the request deadline passed"));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        (&mut &*self.stream).read(buf)
+    }
+}
+
+fn handle_connection(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let request = read_request(&mut DeadlineStream { stream: &stream, deadline: Instant::now() + TIMEOUT });
+    let response = match request {
+        Ok(request) => handle(&request),
+        Err(response) => response
+    };
+    response.write_to(&mut stream)
+}
+
+// answers connections on the listener with a fixed pool of workers. Failed
+// accepts are logged and the listener carries on.
+pub fn serve_listener(listener: TcpListener) -> io::Result<()> {
+    // a rendezvous channel, so connections are only accepted once a worker is free
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(0);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..MAX_CONNECTIONS {
+        let receiver = Arc::clone(&receiver);
+        thread::spawn(move || loop {
+            let stream = match receiver.lock() {
+                Ok(receiver) => receiver.recv(),
+                Err(_) => return
+            };
+            match stream {
+                Ok(stream) => if let Err(err) = handle_connection(stream) {
+                    eprintln!("error: {}", err);
+                },
+                Err(_) => return
+            }
+        });
+    }
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => if sender.send(stream).is_err() {
+                return Err(io::Error::other("every worker has stopped"));
+            },
+            Err(err) => {
+                eprintln!("error: accepting a connection failed: {}", err);
+                thread::sleep(ACCEPT_BACKOFF);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn serve(host: &str, port: u16) -> io::Result<()> {
+    let listener = TcpListener::bind((host, port))?;
+    eprintln!("listening on http://{}", listener.local_addr()?);
+    serve_listener(listener)
+}
@@ -0,0 +1,355 @@
+use alloc::str::FromStr;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use qr_encoder::cell::Color;
+use qr_encoder::error::QRError;
+use qr_encoder::pdf::{PdfPage, write_pdf, text_width};
+use qr_encoder::qr::QR;
+
+const BLACK: Color = Color { r: 0, g: 0, b: 0 };
+const WHITE: Color = Color { r: 255, g: 255, b: 255 };
+
+// space kept clear inside each label's edges, in millimetres
+const LABEL_PADDING: f32 = 1.5;
+const QUIET_ZONE: usize = 4;
+// smaller modules than this don't print or scan reliably
+const MIN_MODULE_SIZE: f32 = 0.25;
+// captions are set in 7 point Helvetica, a little under 2.5mm high
+const CAPTION_SIZE: f32 = 7.0;
+const CAPTION_HEIGHT: f32 = 3.5;
+// cut marks are short hairlines in the margin, clear of the labels
+const CUT_MARK_LENGTH: f32 = 4.0;
+const CUT_MARK_GAP: f32 = 1.0;
+const CUT_MARK_WEIGHT: f32 = 0.1;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PageSize {
+    // millimetres
+    pub width: f32,
+    pub height: f32
+}
+
+pub const A4: PageSize = PageSize { width: 210.0, height: 297.0 };
+pub const LETTER: PageSize = PageSize { width: 215.9, height: 279.4 };
+
+// where the labels go on each page, all in millimetres
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SheetLayout {
+    pub page: PageSize,
+    pub columns: usize,
+    pub rows: usize,
+    // the top left corner of the first label
+    pub left: f32,
+    pub top: f32,
+    pub label_width: f32,
+    pub label_height: f32,
+    // from the corner of one label to the next across and down, taking in
+    // any gap between them
+    pub pitch_x: f32,
+    pub pitch_y: f32,
+    // the caption under each symbol
+    pub captions: bool,
+    // marks in the margins along each label's edges, for cutting plain paper
+    pub cut_marks: bool
+}
+
+impl SheetLayout {
+    // labels filling the page inside an even margin, edge to edge
+    pub fn grid(page: PageSize, columns: usize, rows: usize, margin: f32) -> SheetLayout {
+        let label_width = (page.width - margin * 2.0) / columns as f32;
+        let label_height = (page.height - margin * 2.0) / rows as f32;
+        SheetLayout {
+            page, columns, rows,
+            left: margin,
+            top: margin,
+            label_width,
+            label_height,
+            pitch_x: label_width,
+            pitch_y: label_height,
+            captions: true,
+            cut_marks: false
+        }
+    }
+
+    // the sheets of Avery's label templates by product code
+    pub fn avery(code: &str) -> Option<SheetLayout> {
+        let (page, columns, rows, left, top, label_width, label_height, pitch_x, pitch_y) = match code.to_uppercase().as_str() {
+            // 21 labels of 63.5 x 38.1mm
+            "L7160" => (A4, 3, 7, 7.25, 15.15, 63.5, 38.1, 66.04, 38.1),
+            // 14 labels of 99.1 x 38.1mm
+            "L7163" => (A4, 2, 7, 4.65, 15.15, 99.1, 38.1, 101.6, 38.1),
+            // 65 labels of 38.1 x 21.2mm
+            "L7651" => (A4, 5, 13, 4.75, 10.7, 38.1, 21.2, 40.64, 21.2),
+            // 30 labels of 2 5/8 x 1 inch
+            "5160" => (LETTER, 3, 10, 4.7625, 12.7, 66.675, 25.4, 69.85, 25.4),
+            // 20 labels of 4 x 1 inch
+            "5161" => (LETTER, 2, 10, 3.96875, 12.7, 101.6, 25.4, 104.775, 25.4),
+            _ => return None
+        };
+        Some(SheetLayout {
+            page, columns, rows, left, top, label_width, label_height, pitch_x, pitch_y,
+            captions: true,
+            cut_marks: false
+        })
+    }
+
+    pub fn per_page(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    // the top left corner of the `index`th label on its page
+    fn label_corner(&self, index: usize) -> (f32, f32) {
+        let slot = index % self.per_page();
+        let (row, col) = (slot / self.columns, slot % self.columns);
+        (self.left + col as f32 * self.pitch_x, self.top + row as f32 * self.pitch_y)
+    }
+}
+
+fn invalid_layout(s: &str) -> QRError {
+    QRError::InvalidStyle(format!(
+        "{:?} is not a sheet layout, expected a4:COLUMNSxROWS, letter:COLUMNSxROWS or avery-CODE \
+(L7160, L7163, L7651, 5160 or 5161)", s
+    ))
+}
+
+// `a4:3x8` or `letter:4x6` for a grid inside a 10mm margin, or `avery-L7160`
+impl FromStr for SheetLayout {
+    type Err = QRError;
+
+    fn from_str(s: &str) -> Result<SheetLayout, QRError> {
+        let lower = s.to_lowercase();
+        if let Some(code) = lower.strip_prefix("avery-") {
+            return SheetLayout::avery(code).ok_or_else(|| invalid_layout(s));
+        }
+
+        let mut parts = lower.splitn(2, ':');
+        let page = match parts.next() {
+            Some("a4") => A4,
+            Some("letter") => LETTER,
+            _ => return Err(invalid_layout(s))
+        };
+        let mut counts = parts.next().unwrap_or("").splitn(2, 'x');
+        let mut count = || counts.next()
+            .and_then(|n| n.trim().parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .ok_or_else(|| invalid_layout(s));
+        let (columns, rows) = (count()?, count()?);
+        Ok(SheetLayout::grid(page, columns, rows, 10.0))
+    }
+}
+
+// a symbol and the text printed under it
+pub struct Label {
+    pub qr: QR,
+    pub caption: String
+}
+
+// where a symbol and its caption sit within a label
+struct Placement {
+    symbol_x: f32,
+    symbol_y: f32,
+    module_size: f32,
+    caption_y: f32
+}
+
+impl Placement {
+    // the symbol as large as fits above the caption, the two centred in the label
+    fn new(layout: &SheetLayout, qr: &QR) -> Result<Placement, QRError> {
+        let caption = if layout.captions { CAPTION_HEIGHT } else { 0.0 };
+        let available_width = layout.label_width - LABEL_PADDING * 2.0;
+        let available_height = layout.label_height - LABEL_PADDING * 2.0 - caption;
+        let size = available_width.min(available_height).max(0.0);
+        let module_size = size / (qr.width() + QUIET_ZONE * 2) as f32;
+        if module_size < MIN_MODULE_SIZE {
+            return Err(QRError::InvalidStyle(format!(
+                "a {} module symbol would be printed with {:.2}mm modules, below the {}mm that scans reliably",
+                qr.width(), module_size, MIN_MODULE_SIZE
+            )));
+        }
+        let symbol_size = qr.width() as f32 * module_size;
+        let quiet = QUIET_ZONE as f32 * module_size;
+        let top = (layout.label_height - size - caption) / 2.0;
+        Ok(Placement {
+            symbol_x: (layout.label_width - symbol_size) / 2.0,
+            symbol_y: top + quiet,
+            module_size,
+            // the baseline, just under the symbol's quiet zone
+            caption_y: top + size + CAPTION_HEIGHT - 1.0
+        })
+    }
+}
+
+// cuts the caption down to fit the label, ending it with an ellipsis
+fn fit_caption(caption: &str, width: f32) -> String {
+    let caption: String = caption.chars().filter(|c| !c.is_control()).collect();
+    if text_width(&caption, CAPTION_SIZE) <= width {
+        return caption;
+    }
+    let mut fitted = String::new();
+    for c in caption.chars() {
+        fitted.push(c);
+        if text_width(&fitted, CAPTION_SIZE) + text_width("...", CAPTION_SIZE) > width {
+            fitted.pop();
+            break;
+        }
+    }
+    fitted.push_str("...");
+    fitted
+}
+
+// the cut marks for a page as rectangles: a short line in the margin at
+// either end of every label edge
+fn cut_marks(layout: &SheetLayout) -> Vec<(f32, f32, f32, f32)> {
+    let mut xs = vec![];
+    let mut ys = vec![];
+    for col in 0..layout.columns {
+        xs.push(layout.left + col as f32 * layout.pitch_x);
+        xs.push(layout.left + col as f32 * layout.pitch_x + layout.label_width);
+    }
+    for row in 0..layout.rows {
+        ys.push(layout.top + row as f32 * layout.pitch_y);
+        ys.push(layout.top + row as f32 * layout.pitch_y + layout.label_height);
+    }
+    xs.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+    ys.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+
+    let bottom = layout.top + (layout.rows - 1) as f32 * layout.pitch_y + layout.label_height;
+    let right = layout.left + (layout.columns - 1) as f32 * layout.pitch_x + layout.label_width;
+    let half = CUT_MARK_WEIGHT / 2.0;
+    let mut marks = vec![];
+    for &x in xs.iter() {
+        marks.push((x - half, layout.top - CUT_MARK_GAP - CUT_MARK_LENGTH, CUT_MARK_WEIGHT, CUT_MARK_LENGTH));
+        marks.push((x - half, bottom + CUT_MARK_GAP, CUT_MARK_WEIGHT, CUT_MARK_LENGTH));
+    }
+    for &y in ys.iter() {
+        marks.push((layout.left - CUT_MARK_GAP - CUT_MARK_LENGTH, y - half, CUT_MARK_LENGTH, CUT_MARK_WEIGHT));
+        marks.push((right + CUT_MARK_GAP, y - half, CUT_MARK_LENGTH, CUT_MARK_WEIGHT));
+    }
+    // marks that would fall off the page are left out
+    marks.retain(|&(x, y, w, h)| x >= 0.0 && y >= 0.0 && x + w <= layout.page.width && y + h <= layout.page.height);
+    marks
+}
+
+fn check_layout(labels: &[Label], layout: &SheetLayout) -> Result<(), QRError> {
+    if labels.is_empty() {
+        return Err(QRError::InvalidPayload(String::from("there are no labels to lay out")));
+    }
+    if layout.columns == 0 || layout.rows == 0 {
+        return Err(QRError::InvalidStyle(String::from("a sheet needs at least one column and one row")));
+    }
+    let right = layout.left + (layout.columns - 1) as f32 * layout.pitch_x + layout.label_width;
+    let bottom = layout.top + (layout.rows - 1) as f32 * layout.pitch_y + layout.label_height;
+    if layout.left < 0.0 || layout.top < 0.0 || right > layout.page.width + 0.01 || bottom > layout.page.height + 0.01 {
+        return Err(QRError::InvalidStyle(String::from("the labels don't fit on the page")));
+    }
+    if layout.label_width <= LABEL_PADDING * 2.0 || layout.label_height <= LABEL_PADDING * 2.0 + CAPTION_HEIGHT {
+        return Err(QRError::InvalidStyle(String::from("the labels are too small to hold a symbol")));
+    }
+    Ok(())
+}
+
+// the labels laid out on as many PDF pages as they need
+pub fn to_sheet_pdf(labels: &[Label], layout: &SheetLayout) -> Result<Vec<u8>, QRError> {
+    check_layout(labels, layout)?;
+    let mut pages: Vec<PdfPage> = vec![];
+    for (i, label) in labels.iter().enumerate() {
+        if i % layout.per_page() == 0 {
+            let mut page = PdfPage::new(layout.page.width, layout.page.height);
+            if layout.cut_marks {
+                for &(x, y, w, h) in cut_marks(layout).iter() {
+                    page.fill_rect(x, y, w, h, BLACK);
+                }
+            }
+            pages.push(page);
+        }
+        let page = pages.last_mut().unwrap();
+        let (x, y) = layout.label_corner(i);
+        let placement = Placement::new(layout, &label.qr)?;
+
+        // an inverted symbol needs its dark quiet zone painted in
+        let background = if label.qr.inverted { BLACK } else { WHITE };
+        if label.qr.inverted {
+            let quiet = QUIET_ZONE as f32 * placement.module_size;
+            let size = (label.qr.width() + QUIET_ZONE * 2) as f32 * placement.module_size;
+            page.fill_rect(x + placement.symbol_x - quiet, y + placement.symbol_y - quiet, size, size, BLACK);
+        }
+        page.draw_symbol(&label.qr, x + placement.symbol_x, y + placement.symbol_y, placement.module_size, background);
+
+        if layout.captions {
+            let caption = fit_caption(&label.caption, layout.label_width - LABEL_PADDING * 2.0);
+            let width = text_width(&caption, CAPTION_SIZE);
+            page.text(x + (layout.label_width - width) / 2.0, y + placement.caption_y, CAPTION_SIZE, &caption, BLACK);
+        }
+    }
+    Ok(write_pdf(&pages))
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+// the labels laid out as SVG documents, one a page, sized in millimetres
+pub fn to_sheet_svgs(labels: &[Label], layout: &SheetLayout) -> Result<Vec<String>, QRError> {
+    check_layout(labels, layout)?;
+    let mut pages: Vec<String> = vec![];
+    for (i, label) in labels.iter().enumerate() {
+        if i % layout.per_page() == 0 {
+            let mut page = format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" width=\"{0}mm\" height=\"{1}mm\" viewBox=\"0 0 {0} {1}\">\n\
+<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n",
+                layout.page.width, layout.page.height
+            );
+            if layout.cut_marks {
+                for &(x, y, w, h) in cut_marks(layout).iter() {
+                    page.push_str(&format!("<rect x=\"{:.3}\" y=\"{:.3}\" width=\"{:.3}\" height=\"{:.3}\" fill=\"#000000\"/>\n", x, y, w, h));
+                }
+            }
+            pages.push(page);
+        }
+        let page = pages.last_mut().unwrap();
+        let (x, y) = layout.label_corner(i);
+        let placement = Placement::new(layout, &label.qr)?;
+        let qr = &label.qr;
+
+        // each symbol is drawn in modules and scaled into place
+        let quiet = QUIET_ZONE as f32 * placement.module_size;
+        let (background, foreground) = if qr.inverted { ("#000000", "#ffffff") } else { ("#ffffff", "#000000") };
+        let mut path = String::new();
+        for row in 0..qr.width() {
+            for col in 0..qr.width() {
+                if qr.is_dark(row, col) != qr.inverted {
+                    path.push_str(&format!("M{},{}h1v1h-1z", col + QUIET_ZONE, row + QUIET_ZONE));
+                }
+            }
+        }
+        page.push_str(&format!(
+            "<g transform=\"translate({:.3} {:.3}) scale({:.5})\" shape-rendering=\"crispEdges\">\
+<rect width=\"{3}\" height=\"{3}\" fill=\"{4}\"/><path fill=\"{5}\" d=\"{6}\"/></g>\n",
+            x + placement.symbol_x - quiet, y + placement.symbol_y - quiet, placement.module_size,
+            qr.width() + QUIET_ZONE * 2, background, foreground, path
+        ));
+
+        if layout.captions {
+            let caption = fit_caption(&label.caption, layout.label_width - LABEL_PADDING * 2.0);
+            page.push_str(&format!(
+                "<text x=\"{:.3}\" y=\"{:.3}\" font-family=\"Helvetica, Arial, sans-serif\" font-size=\"{:.3}\" text-anchor=\"middle\">{}</text>\n",
+                x + layout.label_width / 2.0, y + placement.caption_y, CAPTION_SIZE * 25.4 / 72.0, xml_escape(&caption)
+            ));
+        }
+    }
+    for page in pages.iter_mut() {
+        page.push_str("</svg>\n");
+    }
+    Ok(pages)
+}
@@ -0,0 +1,167 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use ed25519_dalek::{Signature, Signer, SIGNATURE_LENGTH};
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+
+use qr_encoder::capacity::max_characters;
+use qr_encoder::compact::{base45_encode, base45_decode};
+use qr_encoder::config::{QRConfig, ECLevel, EncodingMode};
+use qr_encoder::error::QRError;
+use qr_encoder::segment::Segment;
+use qr_encoder::version::Version;
+
+// a signed payload is laid out as
+//
+//   byte 0          the layout version, 1
+//   bytes 1 to n    the data, unchanged
+//   last 64 bytes   an Ed25519 signature over the layout version and the data
+//
+// and carried in the symbol in one of three ways:
+//
+//   binary          the bytes as they are, in a byte segment
+//   Base45          `S45:` then the bytes in Base45, all in an alphanumeric segment
+//   Base64          `S64:` then the bytes in unpadded URL-safe Base64, in a byte segment
+pub const LAYOUT_VERSION: u8 = 1;
+
+// the bytes signing adds to the data
+pub const SIGNATURE_OVERHEAD: usize = 1 + SIGNATURE_LENGTH;
+
+const BASE45_PREFIX: &[u8] = b"S45:";
+const BASE64_PREFIX: &[u8] = b"S64:";
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Transport {
+    Binary,
+    // for scanners that only hand back text; 8.25 bits a byte in alphanumeric mode
+    Base45,
+    Base64
+}
+
+fn undecodable(reason: &str) -> QRError {
+    QRError::Undecodable(String::from(reason))
+}
+
+// RFC 4648 URL-safe Base64 without padding
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() * 4).div_ceil(3));
+    for chunk in data.chunks(3) {
+        let mut buffer = 0;
+        for (i, byte) in chunk.iter().enumerate() {
+            buffer |= (*byte as u32) << (16 - i * 8);
+        }
+        for i in 0..chunk.len() + 1 {
+            encoded.push(BASE64_ALPHABET[((buffer >> (18 - i * 6)) & 63) as usize] as char);
+        }
+    }
+    encoded
+}
+
+pub fn base64_decode(encoded: &[u8]) -> Result<Vec<u8>, QRError> {
+    if encoded.len() % 4 == 1 {
+        return Err(undecodable("the data is not valid Base64"));
+    }
+    let mut data = Vec::with_capacity(encoded.len() * 3 / 4);
+    for chunk in encoded.chunks(4) {
+        let mut buffer = 0;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = match BASE64_ALPHABET.iter().position(|a| a == c) {
+                Some(value) => value as u32,
+                None => return Err(undecodable("the data is not valid Base64"))
+            };
+            buffer |= value << (18 - i * 6);
+        }
+        for i in 0..chunk.len() - 1 {
+            data.push((buffer >> (16 - i * 8)) as u8);
+        }
+    }
+    Ok(data)
+}
+
+// the data in the signed layout
+pub fn sign(data: &[u8], key: &SigningKey) -> Vec<u8> {
+    let mut signed = Vec::with_capacity(data.len() + SIGNATURE_OVERHEAD);
+    signed.push(LAYOUT_VERSION);
+    signed.extend_from_slice(data);
+    let signature = key.sign(&signed);
+    signed.extend_from_slice(&signature.to_bytes());
+    signed
+}
+
+fn transport_segment(signed: &[u8], transport: Transport) -> Segment {
+    match transport {
+        Transport::Binary => Segment::Byte(signed.to_vec()),
+        Transport::Base45 => {
+            let mut text = BASE45_PREFIX.to_vec();
+            text.extend_from_slice(base45_encode(signed).as_bytes());
+            Segment::AlphaNumeric(text)
+        },
+        Transport::Base64 => {
+            let mut text = BASE64_PREFIX.to_vec();
+            text.extend_from_slice(base64_encode(signed).as_bytes());
+            Segment::Byte(text)
+        }
+    }
+}
+
+// the most bytes of data that fit in the symbol once signed
+pub fn signed_capacity(version: Version, err_correction_level: &ECLevel, transport: Transport) -> usize {
+    let bytes = match transport {
+        Transport::Binary => max_characters(version, err_correction_level, &EncodingMode::Byte),
+        Transport::Base45 => {
+            // three characters for every two bytes and two for a byte left over
+            let characters = max_characters(version, err_correction_level, &EncodingMode::AlphaNumeric)
+                .saturating_sub(BASE45_PREFIX.len());
+            characters / 3 * 2 + if characters % 3 == 2 { 1 } else { 0 }
+        },
+        Transport::Base64 => {
+            // four characters for every three bytes, and one more than the
+            // bytes left over
+            let characters = max_characters(version, err_correction_level, &EncodingMode::Byte)
+                .saturating_sub(BASE64_PREFIX.len());
+            characters / 4 * 3 + (characters % 4).saturating_sub(1)
+        }
+    };
+    bytes.saturating_sub(SIGNATURE_OVERHEAD)
+}
+
+// signs the data and makes a config for it, in the smallest version that
+// fits when no version is given
+pub fn encode_signed(data: &[u8], key: &SigningKey, transport: Transport, version: Option<Version>, err_correction_level: ECLevel) -> Result<QRConfig, QRError> {
+    let largest = version.unwrap_or_else(Version::largest);
+    let capacity = signed_capacity(largest, &err_correction_level, transport);
+    if data.len() > capacity {
+        return Err(QRError::DataTooLong { length: data.len(), capacity });
+    }
+
+    let segment = transport_segment(&sign(data, key), transport);
+    QRConfig::from_segments(vec![segment], version, err_correction_level)
+}
+
+// checks the signature of a payload read from a symbol, in any of the
+// transports, and returns the data it covers
+pub fn verify(decoded: &[u8], public_key: &VerifyingKey) -> Result<Vec<u8>, QRError> {
+    let signed = if decoded.starts_with(BASE45_PREFIX) {
+        base45_decode(&decoded[BASE45_PREFIX.len()..])?
+    } else if decoded.starts_with(BASE64_PREFIX) {
+        base64_decode(&decoded[BASE64_PREFIX.len()..])?
+    } else {
+        decoded.to_vec()
+    };
+
+    if signed.len() < SIGNATURE_OVERHEAD {
+        return Err(undecodable("the payload is too short to be signed"));
+    }
+    if signed[0] != LAYOUT_VERSION {
+        return Err(QRError::Undecodable(format!("signed layout version {} is not supported", signed[0])));
+    }
+
+    let (message, signature) = signed.split_at(signed.len() - SIGNATURE_LENGTH);
+    let mut bytes = [0; SIGNATURE_LENGTH];
+    bytes.copy_from_slice(signature);
+    public_key.verify_strict(message, &Signature::from_bytes(&bytes))
+        .map_err(|_| QRError::InvalidSignature)?;
+    Ok(message[1..].to_vec())
+}
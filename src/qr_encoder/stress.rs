@@ -0,0 +1,225 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::str::FromStr;
+
+use qr_encoder::capacity::EC_LEVELS;
+use qr_encoder::config::{QRConfig, ECLevel};
+use qr_encoder::decode::{MatrixDecoder, version_for_width};
+use qr_encoder::error::QRError;
+use qr_encoder::qr::QR;
+use qr_encoder::util::codeword_info;
+use qr_encoder::version::Version;
+
+// a small xorshift generator, so runs repeat exactly for a given seed
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // xorshift never leaves zero
+        Rng(if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    // a number below `bound`
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+// what happens to a damaged module. Flipped modules read as the wrong colour
+// without the decoder knowing, erased ones are known to be unreadable, which
+// costs half as much error correction.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DamageKind {
+    Flip,
+    Erase
+}
+
+// which modules get damaged
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DamageRegion {
+    // this many modules picked at random anywhere in the symbol
+    Random(usize),
+    // a run of this many modules in reading order, from a random start
+    Burst(usize),
+    // a fixed block of modules
+    Rectangle { row: usize, col: usize, width: usize, height: usize }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Damage {
+    pub kind: DamageKind,
+    pub region: DamageRegion
+}
+
+fn invalid_region(s: &str) -> QRError {
+    QRError::InvalidDamage(String::from(s))
+}
+
+impl FromStr for DamageRegion {
+    type Err = QRError;
+
+    fn from_str(s: &str) -> Result<DamageRegion, QRError> {
+        let mut parts = s.splitn(2, ':');
+        let kind = parts.next().unwrap_or("");
+        let numbers: Vec<usize> = match parts.next() {
+            Some(numbers) => numbers.split(',')
+                .map(|n| n.trim().parse::<usize>().map_err(|_| invalid_region(s)))
+                .collect::<Result<Vec<usize>, QRError>>()?,
+            None => return Err(invalid_region(s))
+        };
+
+        match (kind, numbers.len()) {
+            ("random", 1) => Ok(DamageRegion::Random(numbers[0])),
+            ("burst", 1) => Ok(DamageRegion::Burst(numbers[0])),
+            ("rect", 4) => Ok(DamageRegion::Rectangle { row: numbers[0], col: numbers[1], width: numbers[2], height: numbers[3] }),
+            _ => Err(invalid_region(s))
+        }
+    }
+}
+
+// damages a module matrix in place. Erased modules are also set light, as a
+// scanner would see a scuffed or covered area.
+pub fn apply_damage(modules: &mut [u8], erased: &mut [bool], width: usize, damage: &Damage, rng: &mut Rng) {
+    let total = modules.len();
+    let mut hit = |idx: usize| match damage.kind {
+        DamageKind::Flip => modules[idx] ^= 1,
+        DamageKind::Erase => {
+            modules[idx] = 0;
+            erased[idx] = true;
+        }
+    };
+
+    match damage.region {
+        DamageRegion::Random(count) => {
+            // distinct modules, so flipping one twice doesn't undo the damage:
+            // a partial shuffle, each pick swapped out of the ones left
+            let mut indices: Vec<usize> = (0..total).collect();
+            for i in 0..count.min(total) {
+                indices.swap(i, i + rng.below(total - i));
+                hit(indices[i]);
+            }
+        },
+        DamageRegion::Burst(length) => {
+            let length = length.min(total);
+            let start = rng.below(total - length + 1);
+            for idx in start..(start + length) {
+                hit(idx);
+            }
+        },
+        DamageRegion::Rectangle { row, col, width: w, height: h } => {
+            for r in row..(row + h).min(width) {
+                for c in col..(col + w).min(width) {
+                    hit(r * width + c);
+                }
+            }
+        }
+    }
+}
+
+// how a symbol held up against repeated damage
+#[derive(Clone, Debug)]
+pub struct StressResult {
+    pub err_correction_level: ECLevel,
+    pub version: usize,
+    pub ecc_codewords: usize,
+    pub blocks: usize,
+    pub trials: usize,
+    // trials where the message decoded back exactly
+    pub successes: usize
+}
+
+impl StressResult {
+    pub fn success_rate(&self) -> f32 {
+        if self.trials == 0 {
+            return 0.0;
+        }
+        self.successes as f32 / self.trials as f32
+    }
+}
+
+// damages the symbol `trials` times, applying every damage in turn, and counts
+// how often `data` still decodes from it
+pub fn stress_qr(qr: &QR, data: &[u8], damages: &[Damage], trials: usize, seed: u64) -> Result<StressResult, QRError> {
+    let qr = qr.restore();
+    let width = qr.width();
+    let version = version_for_width(width)?;
+    let decoder = MatrixDecoder::new(version);
+    let original = qr.modules();
+    let mut rng = Rng::new(seed);
+
+    let mut successes = 0;
+    for _ in 0..trials {
+        let mut modules = original.clone();
+        let mut erased = vec![false; modules.len()];
+        for damage in damages.iter() {
+            apply_damage(&mut modules, &mut erased, width, damage, &mut rng);
+        }
+        if let Ok(message) = decoder.decode(&modules, &erased) {
+            if message == data {
+                successes += 1;
+            }
+        }
+    }
+
+    let properties = codeword_info(version.number(), &qr.err_correction_level);
+    Ok(StressResult {
+        err_correction_level: qr.err_correction_level,
+        version: version.number(),
+        ecc_codewords: properties.ecc_codeword_count,
+        blocks: properties.block_count,
+        trials,
+        successes
+    })
+}
+
+// encodes `data` at each error correction level and stress tests every symbol
+// with the same damage. Every level uses the same version, the one given or
+// else the smallest that fits at the highest level the data fits at, so the
+// damage covers the same modules; levels the data doesn't fit at are left
+// out. Each level uses the same seed, so they see the same sequence of damage.
+pub fn stress(data: &[u8], version: Option<Version>, damages: &[Damage], trials: usize, seed: u64) -> Result<Vec<StressResult>, QRError> {
+    let version = match version {
+        Some(version) => version,
+        None => {
+            // the Low level's error, when the data fits nowhere
+            let mut fitted = Err(QRError::InvalidVersion(0));
+            for level in EC_LEVELS.iter().rev() {
+                fitted = QRConfig::from_data(data.to_vec(), None, *level);
+                if !matches!(fitted, Err(QRError::DataTooLong { .. })) {
+                    break;
+                }
+            }
+            Version::new(fitted?.version)?
+        }
+    };
+
+    // the Low level's error has the most that fits in the version at any level
+    let mut too_long = None;
+    let mut results = vec![];
+    for level in EC_LEVELS.iter() {
+        let mut config = match QRConfig::from_data(data.to_vec(), Some(version), *level) {
+            Ok(config) => config,
+            Err(err @ QRError::DataTooLong { .. }) => {
+                too_long = too_long.or(Some(err));
+                continue;
+            },
+            Err(err) => return Err(err)
+        };
+        let (qr, _) = config.gen_qr_code();
+        results.push(stress_qr(&qr, data, damages, trials, seed)?);
+    }
+
+    match too_long {
+        Some(err) if results.is_empty() => Err(err),
+        _ => Ok(results)
+    }
+}
@@ -0,0 +1,399 @@
+use alloc::str::FromStr;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use qr_encoder::cell::{CellType, Color};
+use qr_encoder::error::QRError;
+use qr_encoder::qr::QR;
+
+// the brightness difference the W3C recommends between text and its background
+pub const MIN_BRIGHTNESS_DIFFERENCE: u32 = 125;
+
+// how dark data modules are drawn. Timing, alignment, format and version
+// modules are always drawn as squares so the function patterns stay intact.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ModuleShape {
+    Square,
+    Circle,
+    // corners are rounded off where a module has no dark neighbours
+    Rounded,
+    // dark modules in a row run together into bars with rounded ends
+    HorizontalBars,
+    VerticalBars
+}
+
+// shape of the outer ring and the inner 3x3 ball of each finder pattern
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EyeShape {
+    Square,
+    Rounded,
+    Circle
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GradientDirection {
+    Horizontal,
+    Vertical,
+    // from the top left corner to the bottom right
+    Diagonal
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Fill {
+    Solid(Color),
+    Linear { from: Color, to: Color, direction: GradientDirection }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EyeStyle {
+    pub outer: EyeShape,
+    pub inner: EyeShape,
+    // colours for the ring and the ball, the module fill when not set
+    pub outer_color: Option<Color>,
+    pub inner_color: Option<Color>
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Style {
+    pub shape: ModuleShape,
+    pub fill: Fill,
+    pub background: Color,
+    pub eyes: EyeStyle,
+    // pixels per module
+    pub module_size: usize,
+    // light modules of padding on every side
+    pub quiet_zone: usize
+}
+
+// a rectangle in module units with a radius for each corner: top left,
+// top right, bottom right and bottom left
+#[derive(Copy, Clone, Debug)]
+pub struct RoundedRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub radii: [f32; 4]
+}
+
+// a finder pattern: the ring between `outer` and `hole`, and the ball
+#[derive(Copy, Clone, Debug)]
+pub struct Eye {
+    pub outer: RoundedRect,
+    pub hole: RoundedRect,
+    pub ball: RoundedRect
+}
+
+// the shapes making up a styled symbol, in module units without the quiet zone
+pub struct StyledSymbol {
+    pub width: usize,
+    pub modules: Vec<RoundedRect>,
+    pub eyes: Vec<Eye>
+}
+
+impl Default for EyeStyle {
+    fn default() -> EyeStyle {
+        EyeStyle { outer: EyeShape::Square, inner: EyeShape::Square, outer_color: None, inner_color: None }
+    }
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        Style {
+            shape: ModuleShape::Square,
+            fill: Fill::Solid(Color { r: 0, g: 0, b: 0 }),
+            background: Color { r: 255, g: 255, b: 255 },
+            eyes: EyeStyle::default(),
+            module_size: 10,
+            quiet_zone: 4
+        }
+    }
+}
+
+impl FromStr for ModuleShape {
+    type Err = QRError;
+
+    fn from_str(shape: &str) -> Result<ModuleShape, QRError> {
+        match shape {
+            "square" => Ok(ModuleShape::Square),
+            "circle" | "dot" => Ok(ModuleShape::Circle),
+            "rounded" => Ok(ModuleShape::Rounded),
+            "hbars" => Ok(ModuleShape::HorizontalBars),
+            "vbars" => Ok(ModuleShape::VerticalBars),
+            _ => Err(QRError::InvalidStyle(format!("unknown module shape {:?}", shape)))
+        }
+    }
+}
+
+impl FromStr for EyeShape {
+    type Err = QRError;
+
+    fn from_str(shape: &str) -> Result<EyeShape, QRError> {
+        match shape {
+            "square" => Ok(EyeShape::Square),
+            "rounded" => Ok(EyeShape::Rounded),
+            "circle" => Ok(EyeShape::Circle),
+            _ => Err(QRError::InvalidStyle(format!("unknown eye shape {:?}", shape)))
+        }
+    }
+}
+
+// parses a `#rrggbb` colour, the `#` being optional
+pub fn parse_color(value: &str) -> Result<Color, QRError> {
+    let digits = value.trim_start_matches('#');
+    let channel = |i: usize| u32::from_str_radix(&digits[i..i + 2], 16);
+    if digits.len() != 6 || !digits.is_ascii() {
+        return Err(QRError::InvalidStyle(format!("colour {:?} is not of the form #rrggbb", value)));
+    }
+
+    match (channel(0), channel(2), channel(4)) {
+        (Ok(r), Ok(g), Ok(b)) => Ok(Color { r, g, b }),
+        _ => Err(QRError::InvalidStyle(format!("colour {:?} is not of the form #rrggbb", value)))
+    }
+}
+
+pub fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+// perceived brightness from 0 to 255, as the W3C accessibility guidelines weigh it
+pub fn brightness(color: Color) -> u32 {
+    (color.r * 299 + color.g * 587 + color.b * 114) / 1000
+}
+
+fn mix(from: Color, to: Color, t: f32) -> Color {
+    let channel = |a: u32, b: u32| (a as f32 + (b as f32 - a as f32) * t + 0.5) as u32;
+    Color { r: channel(from.r, to.r), g: channel(from.g, to.g), b: channel(from.b, to.b) }
+}
+
+impl Fill {
+    // the colour at a point, `x` and `y` running from 0 to 1 across the symbol
+    pub fn color_at(&self, x: f32, y: f32) -> Color {
+        match *self {
+            Fill::Solid(color) => color,
+            Fill::Linear { from, to, direction } => {
+                let t = match direction {
+                    GradientDirection::Horizontal => x,
+                    GradientDirection::Vertical => y,
+                    GradientDirection::Diagonal => (x + y) / 2.0
+                };
+                mix(from, to, t.clamp(0.0, 1.0))
+            }
+        }
+    }
+
+    // every colour the fill passes through is between these
+    pub fn stops(&self) -> Vec<Color> {
+        match *self {
+            Fill::Solid(color) => vec![color],
+            Fill::Linear { from, to, .. } => vec![from, to]
+        }
+    }
+}
+
+impl RoundedRect {
+    fn square(x: f32, y: f32, size: f32, radius: f32) -> RoundedRect {
+        RoundedRect { x, y, width: size, height: size, radii: [radius; 4] }
+    }
+
+    // the rectangle flipped left to right within a symbol `width` modules across
+    pub fn mirrored(&self, width: f32) -> RoundedRect {
+        let [tl, tr, br, bl] = self.radii;
+        RoundedRect { x: width - self.x - self.width, radii: [tr, tl, bl, br], ..*self }
+    }
+
+    pub fn contains(&self, px: f32, py: f32) -> bool {
+        if px < self.x || py < self.y || px > self.x + self.width || py > self.y + self.height {
+            return false;
+        }
+
+        // centres of the corner arcs
+        let centres = [
+            (self.x + self.radii[0], self.y + self.radii[0]),
+            (self.x + self.width - self.radii[1], self.y + self.radii[1]),
+            (self.x + self.width - self.radii[2], self.y + self.height - self.radii[2]),
+            (self.x + self.radii[3], self.y + self.height - self.radii[3])
+        ];
+        for (corner, &(cx, cy)) in centres.iter().enumerate() {
+            let radius = self.radii[corner];
+            let beyond_x = if corner == 0 || corner == 3 { px < cx } else { px > cx };
+            let beyond_y = if corner < 2 { py < cy } else { py > cy };
+            if radius > 0.0 && beyond_x && beyond_y {
+                let (dx, dy) = (px - cx, py - cy);
+                return dx * dx + dy * dy <= radius * radius;
+            }
+        }
+
+        true
+    }
+}
+
+impl Eye {
+    pub fn is_ring(&self, px: f32, py: f32) -> bool {
+        self.outer.contains(px, py) && !self.hole.contains(px, py)
+    }
+
+    pub fn is_ball(&self, px: f32, py: f32) -> bool {
+        self.ball.contains(px, py)
+    }
+
+    // a finder pattern at `(x, y)`, its top left corner in module units
+    pub fn new(x: f32, y: f32, style: &EyeStyle) -> Eye {
+        let factor = |shape: EyeShape| match shape {
+            EyeShape::Square => 0.0,
+            EyeShape::Rounded => 0.28,
+            EyeShape::Circle => 0.5
+        };
+        let outer = factor(style.outer);
+        let inner = factor(style.inner);
+
+        Eye {
+            outer: RoundedRect::square(x, y, 7.0, 7.0 * outer),
+            hole: RoundedRect::square(x + 1.0, y + 1.0, 5.0, 5.0 * outer),
+            ball: RoundedRect::square(x + 2.0, y + 2.0, 3.0, 3.0 * inner)
+        }
+    }
+}
+
+// the outline of a dark module given which of its neighbours (top, right,
+// bottom, left) are dark
+fn module_rect(shape: ModuleShape, x: f32, y: f32, neighbours: [bool; 4]) -> RoundedRect {
+    let [top, right, bottom, left] = neighbours;
+    let round = |open: bool, radius: f32| if open { radius } else { 0.0 };
+
+    match shape {
+        ModuleShape::Square => RoundedRect::square(x, y, 1.0, 0.0),
+        ModuleShape::Circle => RoundedRect::square(x, y, 1.0, 0.5),
+        ModuleShape::Rounded => RoundedRect {
+            x, y, width: 1.0, height: 1.0,
+            radii: [
+                round(!top && !left, 0.4),
+                round(!top && !right, 0.4),
+                round(!bottom && !right, 0.4),
+                round(!bottom && !left, 0.4)
+            ]
+        },
+        ModuleShape::HorizontalBars => RoundedRect {
+            x, y: y + 0.1, width: 1.0, height: 0.8,
+            radii: [round(!left, 0.4), round(!right, 0.4), round(!right, 0.4), round(!left, 0.4)]
+        },
+        ModuleShape::VerticalBars => RoundedRect {
+            x: x + 0.1, y, width: 0.8, height: 1.0,
+            radii: [round(!top, 0.4), round(!top, 0.4), round(!bottom, 0.4), round(!bottom, 0.4)]
+        }
+    }
+}
+
+// top left corners of the three finder patterns, as (row, col)
+pub fn finder_origins(width: usize) -> [(usize, usize); 3] {
+    [(0, 0), (0, width - 7), (width - 7, 0)]
+}
+
+// lays out the shapes for every dark module of the symbol as encoded, with
+// the finder patterns replaced by eyes. The shapes are flipped for a mirrored
+// symbol; for an inverted one renderers paint them in the background colour
+// over the fill.
+pub fn layout(qr: &QR, style: &Style) -> StyledSymbol {
+    let mirrored = qr.mirrored;
+    let qr = &qr.restore();
+    let width = qr.width();
+    let dark = |row: isize, col: isize| {
+        if row < 0 || col < 0 || row as usize >= width || col as usize >= width {
+            return false;
+        }
+        let cell = &qr.body[row as usize * width + col as usize];
+        cell.is_black() && !matches!(cell.module_type, CellType::Finder)
+    };
+
+    let mut modules = vec![];
+    for row in 0..width {
+        for col in 0..width {
+            let cell = &qr.body[row * width + col];
+            if !cell.is_black() {
+                continue;
+            }
+
+            let (x, y) = (col as f32, row as f32);
+            let (r, c) = (row as isize, col as isize);
+            match cell.module_type {
+                CellType::Finder => continue,
+                CellType::Message => {
+                    let neighbours = [dark(r - 1, c), dark(r, c + 1), dark(r + 1, c), dark(r, c - 1)];
+                    modules.push(module_rect(style.shape, x, y, neighbours));
+                },
+                _ => modules.push(RoundedRect::square(x, y, 1.0, 0.0))
+            }
+        }
+    }
+
+    let mut eyes: Vec<Eye> = finder_origins(width).iter()
+        .map(|&(row, col)| Eye::new(col as f32, row as f32, &style.eyes))
+        .collect();
+
+    if mirrored {
+        let span = width as f32;
+        modules = modules.iter().map(|rect| rect.mirrored(span)).collect();
+        eyes = eyes.iter()
+            .map(|eye| Eye { outer: eye.outer.mirrored(span), hole: eye.hole.mirrored(span), ball: eye.ball.mirrored(span) })
+            .collect();
+    }
+
+    StyledSymbol { width, modules, eyes }
+}
+
+// the lengths of the dark and light runs sampled across the middle of an eye,
+// in samples, starting with a dark run
+fn eye_runs(eye: &Eye, horizontal: bool, samples_per_module: usize) -> Vec<usize> {
+    let mut runs: Vec<usize> = vec![];
+    let mut last: Option<bool> = None;
+    for i in 0..(7 * samples_per_module) {
+        let along = (i as f32 + 0.5) / samples_per_module as f32;
+        let (px, py) = if horizontal {
+            (eye.outer.x + along, eye.outer.y + 3.5)
+        } else {
+            (eye.outer.x + 3.5, eye.outer.y + along)
+        };
+        let dark = eye.is_ring(px, py) || eye.is_ball(px, py);
+
+        if last == Some(dark) {
+            let end = runs.len() - 1;
+            runs[end] += 1;
+        } else {
+            runs.push(1);
+            last = Some(dark);
+        }
+    }
+    runs
+}
+
+impl Style {
+    // checks the symbol will still read: every foreground colour needs to be
+    // well darker than the background, and a line through the middle of each
+    // eye has to cross dark and light runs in the 1:1:3:1:1 finder ratio
+    pub fn check(&self) -> Result<(), QRError> {
+        let mut foreground = self.fill.stops();
+        foreground.extend(self.eyes.outer_color.iter().chain(self.eyes.inner_color.iter()).cloned());
+
+        for color in foreground {
+            let background = brightness(self.background);
+            if background < brightness(color) + MIN_BRIGHTNESS_DIFFERENCE {
+                return Err(QRError::LowContrast { foreground: color, background: self.background });
+            }
+        }
+
+        let samples = 8;
+        let eye = Eye::new(0.0, 0.0, &self.eyes);
+        let expected = [1, 1, 3, 1, 1];
+        for horizontal in [true, false].iter() {
+            let runs = eye_runs(&eye, *horizontal, samples);
+            let in_ratio = runs.len() == expected.len() && runs.iter().zip(expected.iter())
+                .all(|(&run, &modules)| (run as isize - (modules * samples) as isize).abs() <= samples as isize / 2);
+            if !in_ratio {
+                return Err(QRError::FinderDistorted);
+            }
+        }
+
+        Ok(())
+    }
+}
+
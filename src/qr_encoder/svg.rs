@@ -0,0 +1,148 @@
+use alloc::string::String;
+
+use qr_encoder::cell::Color;
+use qr_encoder::error::QRError;
+use qr_encoder::payment::{swiss_cross, SWISS_SYMBOL_MM};
+use qr_encoder::qr::QR;
+use qr_encoder::style::{Style, Fill, GradientDirection, RoundedRect, layout, hex};
+
+// renders the symbol as an SVG document. Each dark module becomes a unit
+// square on a path, scaled up by `module_size` pixels, with `quiet_zone`
+// light modules of padding on every side. An inverted symbol gets a dark
+// quiet zone with its light modules drawn on it.
+pub fn to_svg(qr: &QR, module_size: usize, quiet_zone: usize) -> String {
+    let width = qr.width();
+    let (background, foreground) = if qr.inverted { ("#000000", "#ffffff") } else { ("#ffffff", "#000000") };
+    let full_width = width + quiet_zone * 2;
+    let pixels = full_width * module_size;
+    let mut path = String::new();
+
+    for row in 0..width {
+        for col in 0..width {
+            if qr.is_dark(row, col) != qr.inverted {
+                path.push_str(&format!("M{},{}h1v1h-1z", col + quiet_zone, row + quiet_zone));
+            }
+        }
+    }
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {1} {1}\" shape-rendering=\"crispEdges\">\
+<rect width=\"100%\" height=\"100%\" fill=\"{2}\"/>\
+<path fill=\"{3}\" d=\"{4}\"/></svg>\n",
+        pixels, full_width, background, foreground, path
+    )
+}
+
+// renders a Swiss QR-bill symbol at its printed size of 46mm, without a
+// quiet zone, with the Swiss cross over its middle
+pub fn to_swiss_svg(qr: &QR) -> String {
+    let width = qr.width();
+    let mut path = String::new();
+    for row in 0..width {
+        for col in 0..width {
+            if qr.is_dark(row, col) {
+                path.push_str(&format!("M{},{}h1v1h-1z", col, row));
+            }
+        }
+    }
+
+    let mut cross = String::new();
+    for &(ref rect, color) in swiss_cross(width).iter() {
+        cross.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            rect.x, rect.y, rect.width, rect.height, hex(color)
+        ));
+    }
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" width=\"{0}mm\" height=\"{0}mm\" viewBox=\"0 0 {1} {1}\">\
+<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\
+<path fill=\"#000000\" shape-rendering=\"crispEdges\" d=\"{2}\"/>{3}</svg>\n",
+        SWISS_SYMBOL_MM, width, path, cross
+    )
+}
+
+fn rounded_rect_path(rect: &RoundedRect, offset: f32) -> String {
+    let (x, y) = (rect.x + offset, rect.y + offset);
+    let (w, h) = (rect.width, rect.height);
+    let [tl, tr, br, bl] = rect.radii;
+    let arc = |r: f32, x: f32, y: f32| {
+        if r > 0.0 { format!("A{},{} 0 0 1 {},{}", r, r, x, y) } else { String::new() }
+    };
+
+    format!(
+        "M{},{}H{}{}V{}{}H{}{}V{}{}z",
+        x + tl, y,
+        x + w - tr, arc(tr, x + w, y + tr),
+        y + h - br, arc(br, x + w - br, y + h),
+        x + bl, arc(bl, x, y + h - bl),
+        y + tl, arc(tl, x + tl, y)
+    )
+}
+
+fn paint(color: Option<Color>) -> String {
+    match color {
+        Some(color) => hex(color),
+        None => String::from("url(#fill)")
+    }
+}
+
+// the paint for a shape, which is the background colour on an inverted symbol
+fn shape_paint(qr: &QR, style: &Style, color: Option<Color>) -> String {
+    if qr.inverted { hex(style.background) } else { paint(color) }
+}
+
+// renders the symbol with module shapes, eye shapes and a fill from `style`,
+// after checking the style keeps the symbol readable. An inverted symbol is
+// drawn in the background colour on the fill.
+pub fn to_styled_svg(qr: &QR, style: &Style) -> Result<String, QRError> {
+    style.check()?;
+
+    let symbol = layout(qr, style);
+    let quiet_zone = style.quiet_zone as f32;
+    let full_width = symbol.width + style.quiet_zone * 2;
+    let pixels = full_width * style.module_size;
+
+    let (start, end) = (quiet_zone, quiet_zone + symbol.width as f32);
+    let gradient = match style.fill {
+        Fill::Solid(color) => format!(
+            "<linearGradient id=\"fill\"><stop offset=\"0\" stop-color=\"{}\"/></linearGradient>",
+            hex(color)
+        ),
+        Fill::Linear { from, to, direction } => {
+            let (x2, y2) = match direction {
+                GradientDirection::Horizontal => (end, start),
+                GradientDirection::Vertical => (start, end),
+                GradientDirection::Diagonal => (end, end)
+            };
+            format!(
+                "<linearGradient id=\"fill\" gradientUnits=\"userSpaceOnUse\" x1=\"{0}\" y1=\"{0}\" x2=\"{1}\" y2=\"{2}\">\
+<stop offset=\"0\" stop-color=\"{3}\"/><stop offset=\"1\" stop-color=\"{4}\"/></linearGradient>",
+                start, x2, y2, hex(from), hex(to)
+            )
+        }
+    };
+
+    // an inverted symbol is painted with the fill and its shapes in the background colour
+    let canvas = if qr.inverted { String::from("url(#fill)") } else { hex(style.background) };
+    let modules: String = symbol.modules.iter().map(|rect| rounded_rect_path(rect, quiet_zone)).collect();
+    let mut rings = String::new();
+    let mut balls = String::new();
+    for eye in symbol.eyes.iter() {
+        rings.push_str(&rounded_rect_path(&eye.outer, quiet_zone));
+        rings.push_str(&rounded_rect_path(&eye.hole, quiet_zone));
+        balls.push_str(&rounded_rect_path(&eye.ball, quiet_zone));
+    }
+
+    Ok(format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {1} {1}\">\
+<defs>{2}</defs>\
+<rect width=\"100%\" height=\"100%\" fill=\"{3}\"/>\
+<path fill=\"{9}\" d=\"{4}\"/>\
+<path fill=\"{5}\" fill-rule=\"evenodd\" d=\"{6}\"/>\
+<path fill=\"{7}\" d=\"{8}\"/></svg>\n",
+        pixels, full_width, gradient, canvas, modules,
+        shape_paint(qr, style, style.eyes.outer_color), rings, shape_paint(qr, style, style.eyes.inner_color), balls,
+        shape_paint(qr, style, None)
+    ))
+}
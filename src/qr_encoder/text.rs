@@ -0,0 +1,34 @@
+use alloc::string::String;
+
+use qr_encoder::qr::QR;
+
+// renders the symbol as text for a terminal with a dark background, two rows
+// of modules to a line: light modules, quiet zone included, are drawn as
+// block characters and dark modules are left as the terminal's background
+pub fn to_text(qr: &QR, quiet_zone: usize) -> String {
+    let width = qr.width();
+    let full_width = width + quiet_zone * 2;
+    // an inverted symbol has a dark quiet zone
+    let light = |row: usize, col: usize| {
+        if row < quiet_zone || col < quiet_zone || row >= width + quiet_zone || col >= width + quiet_zone {
+            return !qr.inverted;
+        }
+        !qr.is_dark(row - quiet_zone, col - quiet_zone)
+    };
+
+    let mut text = String::new();
+    for row in (0..full_width).step_by(2) {
+        for col in 0..full_width {
+            let top = light(row, col);
+            let bottom = row + 1 < full_width && light(row + 1, col);
+            text.push(match (top, bottom) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' '
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
@@ -0,0 +1,121 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use qr_encoder::config::ECLevel;
+
+// a record of every step the encoder took, for tooling that needs to
+// explain or double check a symbol
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    pub version: usize,
+    pub err_correction_level: Option<ECLevel>,
+    pub segments: Vec<SegmentTrace>,
+    // zero bits appended after the last segment
+    pub terminator_bits: usize,
+    // bits added to reach a codeword boundary after the terminator
+    pub bit_padding: usize,
+    // the alternating 236/17 codewords that fill the remaining capacity
+    pub pad_codewords: Vec<u8>,
+    pub data_codewords: Vec<u8>,
+    pub blocks: Vec<BlockTrace>,
+    pub interleaved: Vec<u8>,
+    pub remainder_bits: usize,
+    pub mask_penalties: Vec<MaskPenalty>,
+    pub mask: usize,
+    pub format_bits: u16,
+    pub version_bits: Option<u32>
+}
+
+#[derive(Debug, Clone)]
+pub struct SegmentTrace {
+    // the mode's name, `Eci` for an ECI designator
+    pub mode: &'static str,
+    pub char_count: usize,
+    // the segment's header and data as a string of 0s and 1s
+    pub bits: String
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockTrace {
+    pub data: Vec<u8>,
+    pub ecc: Vec<u8>
+}
+
+#[derive(Debug, Clone)]
+pub struct MaskPenalty {
+    pub pattern: usize,
+    // penalty for each of the four evaluation rules
+    pub scores: [usize; 4],
+    pub total: usize
+}
+
+pub fn json_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c)
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn json_array<T, F: Fn(&T) -> String>(items: &[T], item: F) -> String {
+    let values: Vec<String> = items.iter().map(item).collect();
+    format!("[{}]", values.join(","))
+}
+
+fn json_bytes(bytes: &[u8]) -> String {
+    json_array(bytes, |b| format!("{}", b))
+}
+
+impl Trace {
+    pub fn to_json(&self) -> String {
+        let level = match self.err_correction_level {
+            Some(level) => json_string(&format!("{:?}", level)),
+            None => String::from("null")
+        };
+        let segments = json_array(&self.segments, |s| format!(
+            "{{\"mode\":{},\"char_count\":{},\"bits\":{}}}",
+            json_string(s.mode), s.char_count, json_string(&s.bits)
+        ));
+        let blocks = json_array(&self.blocks, |b| format!(
+            "{{\"data\":{},\"ecc\":{}}}",
+            json_bytes(&b.data), json_bytes(&b.ecc)
+        ));
+        let penalties = json_array(&self.mask_penalties, |p| format!(
+            "{{\"pattern\":{},\"scores\":[{},{},{},{}],\"total\":{}}}",
+            p.pattern, p.scores[0], p.scores[1], p.scores[2], p.scores[3], p.total
+        ));
+        let version_bits = match self.version_bits {
+            Some(bits) => json_string(&format!("{:018b}", bits)),
+            None => String::from("null")
+        };
+
+        format!(
+            "{{\"version\":{},\"err_correction_level\":{},\"segments\":{},\"terminator_bits\":{},\
+\"bit_padding\":{},\"pad_codewords\":{},\"data_codewords\":{},\"blocks\":{},\"interleaved\":{},\
+\"remainder_bits\":{},\"mask_penalties\":{},\"mask\":{},\"format_bits\":{},\"version_bits\":{}}}",
+            self.version,
+            level,
+            segments,
+            self.terminator_bits,
+            self.bit_padding,
+            json_bytes(&self.pad_codewords),
+            json_bytes(&self.data_codewords),
+            blocks,
+            json_bytes(&self.interleaved),
+            self.remainder_bits,
+            penalties,
+            self.mask,
+            json_string(&format!("{:015b}", self.format_bits)),
+            version_bits
+        )
+    }
+}
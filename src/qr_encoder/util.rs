@@ -1,8 +1,7 @@
-use std::env::{args_os};
-use std::ffi::{OsStr};
+use alloc::vec::Vec;
 
 use qr_encoder::cell::{Cell, Color};
-use qr_encoder::config::{QRConfig, ECLevel, EncodingMode};
+use qr_encoder::config::{ECLevel};
 
 
 // L M Q H
@@ -114,6 +113,51 @@ const EC_CODEWORD_TABLE: [ECCodeWordCount; 40] = [
     ECCodeWordCount(750, 1372, 2040, 2430)
 ];
 
+// row/column centres of the alignment patterns for each version (ISO/IEC 18004 Annex E).
+// Every combination of two centres holds a pattern, except those overlapping a finder pattern.
+const ALIGNMENT_PATTERN_CENTERS: [&[usize]; 40] = [
+    &[],
+    &[6, 18],
+    &[6, 22],
+    &[6, 26],
+    &[6, 30],
+    &[6, 34],
+    &[6, 22, 38],
+    &[6, 24, 42],
+    &[6, 26, 46],
+    &[6, 28, 50],
+    &[6, 30, 54],
+    &[6, 32, 58],
+    &[6, 34, 62],
+    &[6, 26, 46, 66],
+    &[6, 26, 48, 70],
+    &[6, 26, 50, 74],
+    &[6, 30, 54, 78],
+    &[6, 30, 56, 82],
+    &[6, 30, 58, 86],
+    &[6, 34, 62, 90],
+    &[6, 28, 50, 72, 94],
+    &[6, 26, 50, 74, 98],
+    &[6, 30, 54, 78, 102],
+    &[6, 28, 54, 80, 106],
+    &[6, 32, 58, 84, 110],
+    &[6, 30, 58, 86, 114],
+    &[6, 34, 62, 90, 118],
+    &[6, 26, 50, 74, 98, 122],
+    &[6, 30, 54, 78, 102, 126],
+    &[6, 26, 52, 78, 104, 130],
+    &[6, 30, 56, 82, 108, 134],
+    &[6, 34, 60, 86, 112, 138],
+    &[6, 30, 58, 86, 114, 142],
+    &[6, 34, 62, 90, 118, 146],
+    &[6, 30, 54, 78, 102, 126, 150],
+    &[6, 24, 50, 76, 102, 128, 154],
+    &[6, 28, 54, 80, 106, 132, 158],
+    &[6, 32, 58, 84, 110, 136, 162],
+    &[6, 26, 54, 82, 110, 138, 166],
+    &[6, 30, 58, 86, 114, 142, 170]
+];
+
 #[derive(Debug, Clone)]
 pub struct BlockContent {
     pub blocks: usize,
@@ -153,6 +197,29 @@ impl CodeWord {
             BlockContent { blocks: group_two_blocks, codewords_per_block: group_two_capacity }
         )
     }
+
+    // the block each codeword of the interleaved stream comes from: the data
+    // codewords are taken from each block in turn, then the error correction
+    // codewords the same way
+    pub fn interleaved_blocks(&self) -> Vec<usize> {
+        let (group_one, group_two) = self.get_data_cw_total_for_groups();
+        let mut sizes = vec![group_one.codewords_per_block; group_one.blocks];
+        sizes.extend(vec![group_two.codewords_per_block; group_two.blocks]);
+        let longest = sizes.iter().cloned().max().unwrap_or(0);
+
+        let mut blocks = Vec::with_capacity(self.capacity);
+        for i in 0..longest {
+            for (block, size) in sizes.iter().enumerate() {
+                if i < *size {
+                    blocks.push(block);
+                }
+            }
+        }
+        for _ in 0..(self.ecc_codeword_count / self.block_count) {
+            blocks.extend(0..self.block_count);
+        }
+        blocks
+    }
 }
 
 pub fn codeword_info(version: usize, err_correction_level: &ECLevel) -> CodeWord {
@@ -174,6 +241,9 @@ pub fn codeword_info(version: usize, err_correction_level: &ECLevel) -> CodeWord
     }
 }
 
+pub fn alignment_pattern_centers(version: usize) -> &'static [usize] {
+    ALIGNMENT_PATTERN_CENTERS[version - 1]
+}
 
 pub fn get_pixel_points(cell: &Cell) -> Vec<(u32, u32, Color)> {
     let i = ((cell.point.0 * 20) as u32) + 80;
@@ -219,115 +289,25 @@ pub fn set_color(index: usize) -> Color {
         _ => Color { r: 255, g: 255, b: 0 }
     }
 }
-//
-// fn get_content_length(mode: u8, version: usize) -> usize {
-//     let modifier = match version {
-//         1...10 => 0,
-//         11...27 => 2,
-//         _ => 4
-//     };
-//
-//     match mode {
-//         1 => 10 + modifier,
-//         2 => 9 + modifier,
-//         8 => 12 + modifier,
-//         _ => {
-//             if version < 10 {
-//                 8
-//             } else {
-//                 16
-//             }
-//         }
-//     }
-// }
-
-fn get_ec_level(level: &str) -> ECLevel {
-    match level {
-        "l" => ECLevel::Low,
-        "q" => ECLevel::Q,
-        "h" => ECLevel::High,
-        _ => ECLevel::Medium
-    }
-}
+// number of bits used by the character count indicator for a given mode indicator and version
+pub fn get_content_length(mode: u8, version: usize) -> usize {
+    let modifier = match version {
+        1..=9 => 0,
+        10..=26 => 2,
+        _ => 4
+    };
 
-pub fn args() -> QRConfig {
-    /*
-        default options are....
-            if no version, the default version is 21
-
-
-        to do:
-            flag for encoding type - default will be utf-8 (i think?)
-            ???
-
-    */
-    let mut qr_args = args_os();
-    let mut version = 14usize;
-    let mut data: Option<Vec<u8>> = None;
-    let mut ec_level: ECLevel = ECLevel::Medium;
-    // let encoding = 4u8;
-    let mut arg = qr_args.next();
-    let mut debug_mode = false;
-
-    while arg.is_some() {
-        let value = arg.unwrap();
-        if value == OsStr::new("-v") {
-            version = match qr_args.next() {
-                Some(n) => {
-                    let x = n.to_str().unwrap().parse::<usize>();
-                    match x {
-                        Ok(nx) if nx < 81 => nx, // if it fails to parse, or parses a number greater than 81, set it to version 21.
-                        Ok(_) => 21usize,
-                        Err(_) => 21usize
-                    }
-                },
-                None => 21usize
-            }
-        } else if value == OsStr::new("-m") {
-            data = match qr_args.next() {
-                Some(msg) => {
-                    let string = String::from(msg.to_str().unwrap());
-                    Some(string.into_bytes())
-                },
-                None => panic!("sdasd")
-            }
-        } else if value == OsStr::new("-ec") {
-            ec_level = match qr_args.next() {
-                Some(ec) => {
-                    let ec = ec.to_str().unwrap();
-                    get_ec_level(&ec)
-                },
-                None => ECLevel::Medium
+    match mode {
+        1 => 10 + modifier,
+        2 => 9 + modifier,
+        8 => 8 + modifier,
+        _ => {
+            if version < 10 {
+                8
+            } else {
+                16
             }
-        } else if value == OsStr::new("-DEBUG") {
-            debug_mode = true;
         }
-
-
-        arg = qr_args.next();
-    }
-
-    let mut data = data.unwrap();
-    let codeword_properties = codeword_info(version, &ec_level);
-    data.truncate(codeword_properties.capacity - codeword_properties.ecc_codeword_count);
-
-    QRConfig {
-        version: version,
-        data: data,
-        codewords: vec![],
-        codeword_properties: codeword_properties,
-        mask: 1,
-        encoding: 4u8,
-        encoding_mode: EncodingMode::Byte,
-        debug_mode: debug_mode,
-        requires_alignment: version > 1,
-        err_correction_level: ec_level,
-        size: (((version - 1) * 4) + 21),
-        finder_points: [
-            (0, 0),
-            ((square_count(version) - 7), 0),
-            (0, (square_count(version) - 7))
-        ]
     }
 }
 
@@ -355,3 +335,98 @@ pub fn get_indices_for_dimensions(start: isize, step: isize, step_modifier: isiz
 
     indices
 }
+
+#[cfg(test)]
+pub mod tests {
+    use alloc::vec::Vec;
+
+    use qr_encoder::cell::CellType;
+    use qr_encoder::config::{ECLevel, QRConfig};
+    use qr_encoder::qr::QR;
+    use qr_encoder::util::{alignment_pattern_centers, square_count};
+    use qr_encoder::version::Version;
+
+    // the modules reserved for function patterns, worked out from the spec
+    // independently of the encoder
+    pub fn reference_mask(version: usize) -> Vec<bool> {
+        let size = square_count(version);
+        let mut mask = vec![false; size * size];
+        {
+            let mut reserve = |row: usize, col: usize| mask[row * size + col] = true;
+
+            // finder patterns with their separators, and the format areas beside them
+            for i in 0..9 {
+                for j in 0..9 {
+                    reserve(i, j);
+                }
+            }
+            for i in 0..9 {
+                for j in (size - 8)..size {
+                    reserve(i, j);
+                    reserve(j, i);
+                }
+            }
+
+            // timing patterns
+            for i in 0..size {
+                reserve(6, i);
+                reserve(i, 6);
+            }
+
+            let centers = alignment_pattern_centers(version);
+            let last = centers.last().cloned().unwrap_or(0);
+            for &row in centers {
+                for &col in centers {
+                    let overlaps_finder = (row == 6 && (col == 6 || col == last)) || (row == last && col == 6);
+                    if overlaps_finder {
+                        continue;
+                    }
+                    for i in (row - 2)..(row + 3) {
+                        for j in (col - 2)..(col + 3) {
+                            reserve(i, j);
+                        }
+                    }
+                }
+            }
+
+            if version > 6 {
+                for i in 0..6 {
+                    for j in (size - 11)..(size - 8) {
+                        reserve(i, j);
+                        reserve(j, i);
+                    }
+                }
+            }
+        }
+
+        mask
+    }
+
+    #[test]
+    fn test_alignment_pattern_centers() {
+        assert_eq!(alignment_pattern_centers(1), &[] as &[usize]);
+        assert_eq!(alignment_pattern_centers(2), &[6, 18]);
+        assert_eq!(alignment_pattern_centers(32), &[6, 34, 60, 86, 112, 138]);
+        assert_eq!(alignment_pattern_centers(40), &[6, 30, 58, 86, 114, 142, 170]);
+
+        for version in 2..41 {
+            let centers = alignment_pattern_centers(version);
+            assert_eq!(*centers.last().unwrap(), square_count(version) - 7);
+        }
+    }
+
+    #[test]
+    fn test_function_pattern_layout() {
+        for version in 1..41 {
+            let config = QRConfig::new(Version::new(version).unwrap(), vec![], ECLevel::Medium);
+            let mut qr = QR::new(config.create_body(), ECLevel::Medium);
+            qr.setup(&config);
+
+            let expected = reference_mask(version);
+            for (idx, cell) in qr.body.iter().enumerate() {
+                let reserved = !matches!(cell.module_type, CellType::None);
+                assert_eq!(expected[idx], reserved, "version {} module {:?}", version, cell.point);
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use alloc::str::FromStr;
+use alloc::string::ToString;
+
+use qr_encoder::error::QRError;
+use qr_encoder::util::square_count;
+
+// a QR code version, which can only hold 1 to 40
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version(usize);
+
+impl Version {
+    pub const MIN: usize = 1;
+    pub const MAX: usize = 40;
+
+    pub fn new(number: usize) -> Result<Version, QRError> {
+        if (Version::MIN..=Version::MAX).contains(&number) {
+            Ok(Version(number))
+        } else {
+            Err(QRError::InvalidVersion(number.min(i64::MAX as usize) as i64))
+        }
+    }
+
+    pub fn number(&self) -> usize {
+        self.0
+    }
+
+    // number of modules along each side of the symbol
+    pub fn size(&self) -> usize {
+        square_count(self.0)
+    }
+
+    // version 40, the largest symbol there is
+    pub fn largest() -> Version {
+        Version(Version::MAX)
+    }
+
+    // every version, smallest first
+    pub fn all() -> impl Iterator<Item = Version> {
+        (Version::MIN..=Version::MAX).map(Version)
+    }
+}
+
+impl FromStr for Version {
+    type Err = QRError;
+
+    fn from_str(value: &str) -> Result<Version, QRError> {
+        match value.trim().parse::<i64>() {
+            Ok(number) if number < 0 => Err(QRError::InvalidVersion(number)),
+            Ok(number) => Version::new(number as usize),
+            Err(_) => Err(QRError::UnparsableVersion(value.to_string()))
+        }
+    }
+}
@@ -0,0 +1,128 @@
+extern crate wasm_bindgen;
+extern crate js_sys;
+
+use self::wasm_bindgen::prelude::*;
+use self::js_sys::{Object, Reflect, Uint8Array};
+
+use qr_encoder::config::{QRConfig, ECLevel};
+use qr_encoder::error::QRError;
+use qr_encoder::qr::QR;
+use qr_encoder::svg::to_svg;
+use qr_encoder::version::Version;
+
+// options object accepted by `encode` and `svg`, every field is optional:
+//   { version: 1-40, ecLevel: "L" | "M" | "Q" | "H", maximiseEcLevel: bool,
+//     moduleSize: 1-32, quietZone: 0-16, invert: bool, mirror: bool }
+struct Options {
+    version: Option<Version>,
+    err_correction_level: ECLevel,
+    maximise_ec_level: bool,
+    module_size: usize,
+    quiet_zone: usize,
+    invert: bool,
+    mirror: bool
+}
+
+fn get_option(options: &JsValue, key: &str) -> Option<JsValue> {
+    if !options.is_object() {
+        return None;
+    }
+
+    match Reflect::get(options, &JsValue::from_str(key)) {
+        Ok(value) => if value.is_undefined() || value.is_null() { None } else { Some(value) },
+        Err(_) => None
+    }
+}
+
+// the largest module size and quiet zone `svg` draws, the same scale limit as
+// the HTTP service, so a version 40 symbol stays a few megabytes at most
+pub const MAX_MODULE_SIZE: usize = 32;
+pub const MAX_QUIET_ZONE: usize = 16;
+
+fn to_js_error(err: QRError) -> JsValue {
+    JsValue::from_str(&err.to_string())
+}
+
+// JS numbers are doubles, so a version of 2.5 is refused rather than cut down to 2
+pub fn parse_version(number: f64) -> Result<Version, QRError> {
+    if number.fract() != 0.0 {
+        return Err(QRError::UnparsableVersion(number.to_string()));
+    }
+    if number < 0.0 {
+        return Err(QRError::InvalidVersion(number as i64));
+    }
+    Version::new(number as usize)
+}
+
+// a whole number option from `min` to `max`
+pub fn parse_size(name: &str, number: f64, min: usize, max: usize) -> Result<usize, QRError> {
+    if number.fract() != 0.0 || number < min as f64 || number > max as f64 {
+        return Err(QRError::InvalidStyle(format!("{} {} is not a whole number from {} to {}", name, number, min, max)));
+    }
+    Ok(number as usize)
+}
+
+fn parse_options(options: &JsValue) -> Result<Options, JsValue> {
+    let number = |key: &str| get_option(options, key).and_then(|v| v.as_f64());
+    let size = |key: &str, default: usize, min: usize, max: usize| match number(key) {
+        Some(value) => parse_size(key, value, min, max).map_err(to_js_error),
+        None => Ok(default)
+    };
+    let flag = |key: &str| get_option(options, key).and_then(|v| v.as_bool()).unwrap_or(false);
+    let version = match number("version") {
+        Some(version) => Some(parse_version(version).map_err(to_js_error)?),
+        None => None
+    };
+    let err_correction_level = match get_option(options, "ecLevel").and_then(|v| v.as_string()) {
+        Some(level) => level.parse::<ECLevel>().map_err(to_js_error)?,
+        None => ECLevel::Medium
+    };
+
+    Ok(Options {
+        version,
+        err_correction_level,
+        maximise_ec_level: flag("maximiseEcLevel"),
+        module_size: size("moduleSize", 8, 1, MAX_MODULE_SIZE)?,
+        quiet_zone: size("quietZone", 4, 0, MAX_QUIET_ZONE)?,
+        invert: flag("invert"),
+        mirror: flag("mirror")
+    })
+}
+
+fn encode_with(text: &str, options: &Options) -> Result<QR, JsValue> {
+    let data = text.as_bytes().to_vec();
+    let mut config = QRConfig::from_data(data, options.version, options.err_correction_level)
+        .map_err(to_js_error)?;
+    config.maximise_ec_level = options.maximise_ec_level;
+    config.invert = options.invert;
+    config.mirror = options.mirror;
+
+    let (qr, _) = config.gen_qr_code();
+    Ok(qr)
+}
+
+// encodes `text` and returns `{ width, modules, inverted, mirrored }`, where
+// `modules` holds width * width bytes in row-major order, 1 for dark and 0 for
+// light, as the symbol is printed
+#[wasm_bindgen]
+pub fn encode(text: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    let qr = encode_with(text, &parse_options(&options)?)?;
+    let modules = qr.modules();
+    let result = Object::new();
+
+    Reflect::set(&result, &JsValue::from_str("width"), &JsValue::from_f64(qr.width() as f64))?;
+    Reflect::set(&result, &JsValue::from_str("modules"), &Uint8Array::from(&modules[..]))?;
+    Reflect::set(&result, &JsValue::from_str("inverted"), &JsValue::from_bool(qr.inverted))?;
+    Reflect::set(&result, &JsValue::from_str("mirrored"), &JsValue::from_bool(qr.mirrored))?;
+
+    Ok(result.into())
+}
+
+// encodes `text` and returns it rendered as an SVG document
+#[wasm_bindgen]
+pub fn svg(text: &str, options: JsValue) -> Result<String, JsValue> {
+    let options = parse_options(&options)?;
+    let qr = encode_with(text, &options)?;
+
+    Ok(to_svg(&qr, options.module_size, options.quiet_zone))
+}
@@ -0,0 +1,40 @@
+// runs the qr-encode binary, checking which files each run leaves behind
+#![cfg(feature = "cli")]
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+// an empty directory of its own for each run, as the image path is relative
+fn run_in(name: &str, args: &[&str]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("qr-encode-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_qr-encode"))
+        .args(args)
+        .current_dir(&dir)
+        .status()
+        .unwrap();
+    assert!(status.success(), "qr-encode {:?} failed", args);
+    dir
+}
+
+#[test]
+fn test_image_output() {
+    // another output alone doesn't write the image as well
+    let dir = run_in("svg", &["-m", "hello", "--svg", "x.svg"]);
+    assert!(dir.join("x.svg").exists());
+    assert!(!dir.join("qr.png").exists());
+    fs::remove_dir_all(&dir).unwrap();
+
+    // the image goes where -o says, alongside the other outputs
+    let dir = run_in("output", &["-m", "hello", "--svg", "x.svg", "-o", "out.png"]);
+    assert!(dir.join("x.svg").exists() && dir.join("out.png").exists());
+    assert!(!dir.join("qr.png").exists());
+    fs::remove_dir_all(&dir).unwrap();
+
+    // with nothing else asked for, the image goes to qr.png
+    let dir = run_in("default", &["-m", "hello"]);
+    assert!(dir.join("qr.png").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}